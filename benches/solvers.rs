@@ -0,0 +1,78 @@
+// benchmarks of algorithm::raise and select_fn on synthetic terrain families
+// with 10 to 10^7 segments, run with `cargo bench`
+#![allow(clippy::needless_return)]
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+use rain::algorithm;
+use rain::generate;
+use rain::solutions;
+use rain::Problem;
+
+const SEED: u64 = 42;
+const HIGH: u64 = 1000;
+
+// a terrain family: name and generator for a given number of segments
+type Family = (&'static str, fn(usize) -> Vec<u64>);
+
+fn families() -> Vec<Family> {
+    return vec![
+        ("ramp", |n| generate::ramp(n, 0, HIGH)),
+        ("sawtooth", |n| generate::sawtooth(n, 0, HIGH, 3)),
+        ("noise", |n| generate::uniform(n, 0, HIGH, SEED)),
+        ("brownian", |n| generate::midpoint(n, 0, HIGH, 0.5, SEED)),
+        ("plateaus", |n| generate::plateaus(n, 0, HIGH, 1000, SEED)),
+    ];
+}
+
+fn sizes() -> Vec<usize> {
+    return (1..=7).map(|e| 10usize.pow(e)).collect();
+}
+
+fn bench_raise(c: &mut Criterion) {
+    for (name, generator) in families() {
+        let mut group = c.benchmark_group(format!("raise/{}", name));
+        for size in sizes() {
+            let grounds = generator(size);
+            if size >= 1_000_000 {
+                group.sample_size(10);
+            }
+            group.throughput(Throughput::Elements(size as u64));
+            group.bench_with_input(BenchmarkId::from_parameter(size), &grounds, |b, g| {
+                b.iter_batched(
+                    || Problem::new(1, g),
+                    algorithm::raise,
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+        group.finish();
+    }
+}
+
+fn bench_select_fn(c: &mut Criterion) {
+    for (name, generator) in families() {
+        let mut group = c.benchmark_group(format!("select_fn/{}", name));
+        for size in sizes() {
+            let grounds = generator(size);
+            if size >= 1_000_000 {
+                group.sample_size(10);
+            }
+            group.throughput(Throughput::Elements(size as u64));
+            group.bench_with_input(BenchmarkId::from_parameter(size), &grounds, |b, g| {
+                b.iter_batched(
+                    || Problem::new(1, g),
+                    |problem| {
+                        let solver = solutions::select_fn(&problem);
+                        solver(problem)
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_raise, bench_select_fn);
+criterion_main!(benches);
@@ -0,0 +1,49 @@
+/* end to end test of the C interface, see c/Makefile */
+#include <math.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "rain.h"
+
+static int failures = 0;
+
+static void check(int condition, const char *what) {
+    if (!condition) {
+        fprintf(stderr, "FAILED: %s\n", what);
+        failures++;
+    }
+}
+
+int main(void) {
+    const uint64_t heights[] = {3, 1, 6, 4, 8, 9};
+    const double expected[] = {4.0, 4.0, 6.0, 6.0, 8.0, 9.0};
+    const size_t len = sizeof(heights) / sizeof(heights[0]);
+    double levels[6];
+    double covers[6];
+
+    int32_t status = rain_solve(heights, len, 1, levels, covers);
+    check(status == RAIN_OK, "solve returns RAIN_OK");
+    for (size_t i = 0; i < len; i++) {
+        check(fabs(levels[i] - expected[i]) < 1e-9, "levels");
+        check(fabs(covers[i] - (expected[i] - (double)heights[i])) < 1e-9, "water covers");
+    }
+
+    status = rain_solve(heights, len, 1, levels, NULL);
+    check(status == RAIN_OK, "water covers are optional");
+
+    status = rain_solve(NULL, len, 1, levels, NULL);
+    check(status == RAIN_NULL_POINTER, "null heights");
+
+    status = rain_solve(heights, 0, 1, levels, NULL);
+    check(status == RAIN_EMPTY_PROFILE, "empty profile");
+
+    const uint64_t huge[] = {UINT64_MAX, 0};
+    status = rain_solve(huge, 2, 1, levels, NULL);
+    check(status == RAIN_OVERFLOW, "overflow");
+    check(strcmp(rain_strerror(status), "amount of water exceeds u64") == 0, "strerror");
+
+    if (failures == 0) {
+        printf("all C interface tests passed\n");
+    }
+    return failures == 0 ? 0 : 1;
+}
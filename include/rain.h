@@ -0,0 +1,51 @@
+#ifndef RAIN_H
+#define RAIN_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define RAIN_OK 0
+
+#define RAIN_NULL_POINTER 1
+
+#define RAIN_EMPTY_PROFILE 2
+
+#define RAIN_OVERFLOW 3
+
+#define RAIN_NOT_CONSERVED 4
+
+#define RAIN_UNSUPPORTED 5
+
+#define RAIN_PANIC 6
+
+#define RAIN_INVALID_INPUT 7
+
+
+
+/**
+ * Solves `days` of rain on `len` segments with the given `heights`.
+ *
+ * Writes the levels of water or ground into `levels` and the depth of
+ * water into `water_covers`, both must hold `len` values. `water_covers`
+ * may be NULL if it is not needed. Returns `RAIN_OK` or an error code,
+ * the buffers are left untouched on error. An internal error returns
+ * `RAIN_PANIC` instead of unwinding into the caller.
+ *
+ * # Safety
+ * `heights` must point to `len` readable values, `levels` and, unless
+ * NULL, `water_covers` to `len` writable ones.
+ */
+int32_t rain_solve(const uint64_t *heights,
+                   size_t len,
+                   uint64_t days,
+                   double *levels,
+                   double *water_covers);
+
+/**
+ * Returns a static, NUL terminated description of a status code.
+ */
+const char *rain_strerror(int32_t status);
+
+#endif  /* RAIN_H */
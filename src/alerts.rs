@@ -0,0 +1,150 @@
+// module alerts watches named locations of a profile: points of interest
+// like a village or a road, each at a segment and with an optional critical
+// depth of water. Given the depths of a solution it tells which locations
+// are dry, wet or flooded beyond their critical depth.
+use crate::Error;
+
+// Location of a point of interest, position is the index of its segment
+// counted from 0. In input files it is the position alone or a table with
+// position and critical depth; input needs it without the feature serde.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    any(feature = "serde", feature = "toml"),
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "Entry", into = "Entry")
+)]
+pub struct Location {
+    pub position: usize,
+    pub critical: Option<f64>,
+}
+
+// Entry is how a location is written in input files
+#[cfg(any(feature = "serde", feature = "toml"))]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Position(usize),
+    Table {
+        position: usize,
+        critical: Option<f64>,
+    },
+}
+
+#[cfg(any(feature = "serde", feature = "toml"))]
+impl From<Entry> for Location {
+    fn from(entry: Entry) -> Location {
+        return match entry {
+            Entry::Position(position) => Location {
+                position,
+                critical: None,
+            },
+            Entry::Table { position, critical } => Location { position, critical },
+        };
+    }
+}
+
+#[cfg(any(feature = "serde", feature = "toml"))]
+impl From<Location> for Entry {
+    fn from(location: Location) -> Entry {
+        return match location.critical {
+            None => Entry::Position(location.position),
+            Some(critical) => Entry::Table {
+                position: location.position,
+                critical: Some(critical),
+            },
+        };
+    }
+}
+
+// Status of a location
+// Dry: no water on its segment
+// Wet: water, but not beyond the critical depth
+// Alert: the water is deeper than the critical depth
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+    Dry,
+    Wet,
+    Alert,
+}
+
+// Report on one location. excess is the depth beyond the critical depth,
+// negative while the water stays below it, None without a critical depth.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    pub name: String,
+    pub position: usize,
+    pub depth: f64,
+    pub status: Status,
+    pub excess: Option<f64>,
+}
+
+// check reports on all locations, given the depth of water per segment
+// such as Solution::water_covers. Depths within rounding of zero are dry.
+pub fn check<'a, I>(locations: I, depths: &[f64]) -> Result<Vec<Report>, Error>
+where
+    I: IntoIterator<Item = (&'a String, &'a Location)>,
+{
+    let mut reports = Vec::new();
+    for (name, location) in locations {
+        let depth = match depths.get(location.position) {
+            Some(&depth) if depth > crate::zero::EPSILON => depth,
+            Some(_) => 0.0,
+            None => return Err(Error::InvalidInput("location outside the profile")),
+        };
+        let excess = location.critical.map(|critical| depth - critical);
+        let status = match excess {
+            Some(excess) if excess > 0.0 => Status::Alert,
+            _ if depth > 0.0 => Status::Wet,
+            _ => Status::Dry,
+        };
+        reports.push(Report {
+            name: name.clone(),
+            position: location.position,
+            depth,
+            status,
+            excess,
+        });
+    }
+    return Ok(reports);
+}
+
+// alert tells whether any location is flooded beyond its critical depth
+pub fn alert(reports: &[Report]) -> bool {
+    return reports.iter().any(|r| r.status == Status::Alert);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solutions::Solution;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn statuses() {
+        let grounds = [5, 0, 3, 0, 5];
+        let solution = Solution::new(vec![5.0, 3.0, 3.0, 3.0, 5.0], &grounds);
+        let mut locations = BTreeMap::new();
+        let at = |position, critical| Location { position, critical };
+        locations.insert("field".to_string(), at(0, Some(0.0)));
+        locations.insert("road".to_string(), at(1, Some(2.0)));
+        locations.insert("school".to_string(), at(3, Some(4.0)));
+        locations.insert("well".to_string(), at(2, None));
+
+        let reports = check(&locations, &solution.water_covers).unwrap();
+        let statuses: Vec<Status> = reports.iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            [Status::Dry, Status::Alert, Status::Wet, Status::Dry]
+        );
+        assert_eq!(reports[1].name, "road");
+        assert_eq!((reports[1].depth, reports[1].excess), (3.0, Some(1.0)));
+        assert_eq!(reports[2].excess, Some(-1.0));
+        assert_eq!(reports[3].excess, None);
+        assert!(alert(&reports));
+
+        locations.remove("road");
+        assert!(!alert(&check(&locations, &solution.water_covers).unwrap()));
+        locations.insert("far".to_string(), at(5, None));
+        assert!(check(&locations, &solution.water_covers).is_err());
+    }
+}
@@ -6,90 +6,334 @@
 // Where v_underwater is the volume below the highest peak.  At this poin the
 // problem is divided in sub problems.  For each subproblem a new highest peak
 // is found and a new saturation level reached.
-
+//
+// The sub problems are kept on an explicit work stack instead of the call
+// stack, so arbitrarily long profiles cannot overflow it. Each sub problem is
+// a subtree of the Cartesian tree of the profile, whose root is the leftmost
+// highest peak of the range, and land volumes come from prefix sums. Every
+// sub problem takes constant time apart from the segments it writes, and
+// every segment is written once: O(N) per pass.
+
+use crate::events::levels_at;
 use crate::solutions::Solution;
 use crate::Problem;
-use std::iter::successors;
 
-// Collector accumulates results and intermediate results from each
-// recursion call
+// Task is a range of segments still to be divided at its highest peak
+// peak: position of the leftmost highest peak in the range
+// level: water level of the range as set by the task that created it, it
+// is final if the peak turns out to be under water
 #[derive(Debug)]
-struct Collector {
-    segments: Vec<f64>,
-}
-
-impl Collector {
-    // constructs a collector with segment's initial values
-    fn new(size: usize) -> Self {
-        let segments: Vec<f64> = successors(Some(0.0), |s| Some(*s)).take(size).collect();
-        return Collector { segments };
-    }
-    fn set_level(&mut self, level: f64, i: usize) {
-        self.segments[i] = level;
-    }
-}
-
-#[derive(Debug)]
-struct RecursorPars {
+struct Task {
     water: f64,
     start: usize,
     end: usize,
+    peak: usize,
     left_edge_peaks: usize,
     right_edge_peaks: usize,
-    nest: u64,
+    level: f64,
 }
 
-impl RecursorPars {
-    fn new(
-        water: f64,
-        start: usize,
-        end: usize,
-        left_edge_peaks: usize,
-        right_edge_peaks: usize,
-        nest: u64,
-    ) -> Self {
-        return Self {
+// Profile holds the ground of one pass with its Cartesian tree, where each
+// segment has the leftmost highest segment of the ranges left and right of
+// it as children (NONE if the range is empty), and the prefix sums of land.
+struct Profile<'a> {
+    grounds: &'a [u64],
+    land: Vec<f64>,
+    left: Vec<usize>,
+    right: Vec<usize>,
+    root: usize,
+}
+
+const NONE: usize = usize::MAX;
+
+impl<'a> Profile<'a> {
+    fn new(grounds: &'a [u64]) -> Self {
+        let mut land = vec![0.0; grounds.len() + 1];
+        for (i, &g) in grounds.iter().enumerate() {
+            land[i + 1] = land[i] + g as f64;
+        }
+
+        // equal heights stay on the stack, so the leftmost becomes the root
+        let mut left = vec![NONE; grounds.len()];
+        let mut right = vec![NONE; grounds.len()];
+        let mut stack: Vec<usize> = Vec::new();
+        for (i, &g) in grounds.iter().enumerate() {
+            let mut last = NONE;
+            while let Some(&top) = stack.last() {
+                if grounds[top] >= g {
+                    break;
+                }
+                last = stack.pop().unwrap();
+            }
+            left[i] = last;
+            if let Some(&top) = stack.last() {
+                right[top] = i;
+            }
+            stack.push(i);
+        }
+        let root = stack[0];
+
+        return Profile {
+            grounds,
+            land,
+            left,
+            right,
+            root,
+        };
+    }
+
+    // sum of land in the range start..end, end exclusive
+    fn land(&self, start: usize, end: usize) -> f64 {
+        return self.land[end] - self.land[start];
+    }
+
+    // the task covering the whole profile
+    fn first_task(&self, water: f64) -> Task {
+        return Task {
+            water,
+            start: 0,
+            end: self.grounds.len() - 1,
+            peak: self.root,
+            left_edge_peaks: 0,
+            right_edge_peaks: 0,
+            level: 0.0,
+        };
+    }
+
+    // step divides a task at its peak. It writes the final levels of the
+    // task's range it knows of into levels, which holds exactly the range
+    // start..=end, and returns the tasks left and right of the peak.
+    fn step(&self, task: &Task, levels: &mut [f64]) -> (Option<Task>, Option<Task>) {
+        let Task {
             water,
             start,
             end,
+            peak,
+            left_edge_peaks,
+            right_edge_peaks,
+            level,
+        } = *task;
+        let peak_heigth = self.grounds[peak];
+
+        // see if adjacent segments right of the present one are at the same level
+        let n_adjacent_peaks = self.grounds[peak..end + 1]
+            .iter()
+            .take_while(|&&g| g == peak_heigth)
+            .count();
+        let after_peaks = peak + n_adjacent_peaks;
+
+        // check if peak is at extremes of our range
+        let has_left = peak != start;
+        let has_right = after_peaks != end + 1;
+
+        // peaks at the left boundary are a special condition for water distribution
+        // one recursion level down
+        let new_left_edge_peaks = if !has_left { n_adjacent_peaks } else { 0 };
+        let new_right_edge_peaks = if !has_right { n_adjacent_peaks } else { 0 };
+
+        // determine if peaks are under water, then the level set before is final
+        let size = end + 1 - start;
+        let underwater = self.land(start, end + 1) + water > peak_heigth as f64 * size as f64;
+        if underwater {
+            for l in levels.iter_mut() {
+                *l = level;
+            }
+            return (None, None);
+        }
+        for l in levels[peak - start..after_peaks - start].iter_mut() {
+            *l = peak_heigth as f64;
+        }
+
+        // grounds left and right of peak
+        let left_size = peak - start;
+        let left_land = self.land(start, peak);
+        let right_size = end + 1 - after_peaks;
+        let right_land = self.land(after_peaks, end + 1);
+
+        // determine if the present range is ajacent to the edges
+        let at_left_edge: bool = start == 0;
+        let at_right_edge: bool = end == self.grounds.len() - 1;
+
+        let WaterDistribution {
+            left: water_left,
+            right: water_right,
+        } = water_distribution(
+            water,
+            has_left,
+            has_right,
+            peak_heigth,
+            n_adjacent_peaks as f64,
+            (left_size, left_land),
+            (right_size, right_land),
+            at_left_edge,
+            at_right_edge,
             left_edge_peaks,
             right_edge_peaks,
-            nest,
+        );
+
+        let left_task = if has_left {
+            Some(Task {
+                water: water_left,
+                start,
+                end: peak - 1,
+                peak: self.left[peak],
+                left_edge_peaks: new_left_edge_peaks,
+                right_edge_peaks: new_right_edge_peaks,
+                level: (water_left + left_land) / left_size as f64,
+            })
+        } else {
+            None
+        };
+        let right_task = if has_right {
+            Some(Task {
+                water: water_right,
+                start: after_peaks,
+                end,
+                peak: self.right[after_peaks - 1],
+                left_edge_peaks: new_left_edge_peaks,
+                right_edge_peaks: new_right_edge_peaks,
+                level: (water_right + right_land) / right_size as f64,
+            })
+        } else {
+            None
         };
+        return (left_task, right_task);
     }
 }
 
-// raise initialises and calls the recursion function and pieces results together
-pub fn raise(p: Problem) -> Solution {
-    // initialize collector
-    let collector0 = Collector::new(p.groundsize);
-
-    let recursor_pars = RecursorPars::new(p.water_tot as f64, 0, p.groundsize - 1, 0, 0, 0);
-    let collector = recursor(recursor_pars, &p.grounds, collector0);
+// solve_pass divides a profile with a given amount of water until all
+// tasks are done and returns the levels
+fn solve_pass(grounds: &[u64], water: f64) -> Vec<f64> {
+    let profile = Profile::new(grounds);
+    let mut levels = vec![0.0; grounds.len()];
+    run_sequential(&profile, profile.first_task(water), &mut levels);
+    return levels;
+}
 
-    let Collector { segments } = collector;
+// run_sequential works off a task and all tasks it creates, levels holds
+// exactly the range of the task
+fn run_sequential(profile: &Profile, task: Task, levels: &mut [f64]) {
+    let offset = task.start;
+    let mut stack = vec![task];
+    while let Some(task) = stack.pop() {
+        let range = task.start - offset..task.end + 1 - offset;
+        let (left, right) = profile.step(&task, &mut levels[range]);
+        stack.extend(right);
+        stack.extend(left);
+    }
+}
 
-    let levels: Vec<f64> = segments.iter().map(|s| *s).collect();
+// raise runs one pass in each direction and pieces results together
+pub fn raise(p: Problem) -> Solution {
+    let levels = solve_pass(&p.grounds, p.water_tot as f64);
 
     // calculate the water levels in reverse, starting left going right
-    let rev_pars = RecursorPars::new(p.water_tot as f64, 0, p.groundsize - 1, 0, 0, 0);
-    let rev_grounds: Vec<u64> = p.grounds.clone().iter().rev().map(|a| *a).collect();
-    let rev_coll0 = Collector::new(p.groundsize);
-    let rev_collector = recursor(rev_pars, &rev_grounds, rev_coll0);
-
-    // then average results of both calculations
-    let average_levels: Vec<f64> = rev_collector
-        .segments
+    let rev_grounds: Vec<u64> = p.grounds.iter().rev().copied().collect();
+    let rev_levels = solve_pass(&rev_grounds, p.water_tot as f64);
+
+    return average(&p.grounds, levels, rev_levels);
+}
+
+// average results of both calculations
+fn average(grounds: &[u64], levels: Vec<f64>, rev_levels: Vec<f64>) -> Solution {
+    let average_levels: Vec<f64> = rev_levels
         .iter()
         .rev()
         .zip(levels)
         .map(|(a, b)| (a + b) / 2.0)
         .collect();
-    return Solution::new(average_levels, &p.grounds);
+    return Solution::new(average_levels, grounds);
+}
+
+// tasks with fewer segments than this are not worth spreading over threads
+#[cfg(feature = "parallel")]
+const PARALLEL_CUTOFF: usize = 1 << 14;
+
+// raise_parallel gives the same result as raise. Once a peak divides a range
+// the tasks left and right of it write to disjoint parts of the levels, large
+// ones run on the rayon thread pool. Both passes run in parallel, too.
+#[cfg(feature = "parallel")]
+pub fn raise_parallel(p: Problem) -> Solution {
+    let water = p.water_tot as f64;
+    let rev_grounds: Vec<u64> = p.grounds.iter().rev().copied().collect();
+    let (levels, rev_levels) = rayon::join(
+        || solve_pass_parallel(&p.grounds, water, PARALLEL_CUTOFF),
+        || solve_pass_parallel(&rev_grounds, water, PARALLEL_CUTOFF),
+    );
+    return average(&p.grounds, levels, rev_levels);
+}
+
+#[cfg(feature = "parallel")]
+fn solve_pass_parallel(grounds: &[u64], water: f64, cutoff: usize) -> Vec<f64> {
+    let profile = Profile::new(grounds);
+    let mut levels = vec![0.0; grounds.len()];
+    run_parallel(&profile, profile.first_task(water), &mut levels, cutoff);
+    return levels;
+}
+
+// run_parallel splits the levels at each peak. Only if both sides are large
+// they are joined on the thread pool, otherwise the smaller side is done
+// right away and the loop goes on with the larger. Thus nested joins are
+// bounded by the number of segments over the cutoff.
+#[cfg(feature = "parallel")]
+fn run_parallel(profile: &Profile, task: Task, levels: &mut [f64], cutoff: usize) {
+    let mut task = task;
+    let mut levels = levels;
+    loop {
+        if levels.len() < cutoff {
+            run_sequential(profile, task, levels);
+            return;
+        }
+        let offset = task.start;
+        let (left, right) = profile.step(&task, levels);
+        let rest = std::mem::take(&mut levels);
+        match (left, right) {
+            (Some(left), Some(right)) => {
+                let (left_levels, rest) = rest.split_at_mut(left.end + 1 - offset);
+                let right_levels = &mut rest[right.start - left.end - 1..];
+                if left_levels.len() >= cutoff && right_levels.len() >= cutoff {
+                    rayon::join(
+                        || run_parallel(profile, left, left_levels, cutoff),
+                        || run_parallel(profile, right, right_levels, cutoff),
+                    );
+                    return;
+                }
+                if left_levels.len() < right_levels.len() {
+                    run_sequential(profile, left, left_levels);
+                    task = right;
+                    levels = right_levels;
+                } else {
+                    run_sequential(profile, right, right_levels);
+                    task = left;
+                    levels = left_levels;
+                }
+            }
+            (Some(left), None) => {
+                levels = &mut rest[..left.end + 1 - offset];
+                task = left;
+            }
+            (None, Some(right)) => {
+                levels = &mut rest[right.start - offset..];
+                task = right;
+            }
+            (None, None) => return,
+        }
+    }
+}
+
+// symmetric solves a problem in a single pass without averaging. It follows
+// the fill-and-spill simulation of module events up to the duration of rain:
+// equal peaks are one node of the merge tree and a peak at a boundary gives
+// all its rain to its only side, so there is no preferred direction and the
+// result of a mirrored profile is exactly the mirrored result.
+pub fn symmetric(p: Problem) -> Solution {
+    let rain = vec![1.0; p.groundsize];
+    let levels = levels_at(&p.grounds, &rain, p.water_0);
+    return Solution::new(levels, &p.grounds);
 }
 
 // well_volume calculates volume of a well from its ground semgments and height
-fn well_volume(gs: &[u64], heigth: u64) -> f64 {
+pub(crate) fn well_volume(gs: &[u64], heigth: u64) -> f64 {
     let volume = heigth as f64 * gs.len() as f64;
     let land: f64 = gs.iter().map(|g| *g as f64).sum();
     return volume - land;
@@ -104,14 +348,16 @@ struct WaterDistribution {
 // distributes water like rain: evenly by area. As long as there is enough room
 // on both sides to take in water.  When one side reaches saturation it
 // distributes water by accounting for displacment by submerged land mass.
+// Ranges left and right of the peak are given by their size and land volume.
+#[allow(clippy::too_many_arguments)]
 fn water_distribution(
     water: f64,
     has_left: bool,
     has_right: bool,
     peak_heigth: u64,
     peak_width: f64,
-    left_grounds: &[u64],
-    right_grounds: &[u64],
+    left_grounds: (usize, f64),
+    right_grounds: (usize, f64),
     at_left_edge: bool,
     at_right_edge: bool,
     left_edge_peaks: usize,
@@ -134,7 +380,7 @@ fn water_distribution(
     // If both ranges have parts that are still submerged displacment is not limiting water
     // uptake. Water is distributed by ranges only, that represents the area it rains upon.
     // The peak(s) separating left and right ranges are distributed evenly.
-    let f_dist_range = |gs: &[u64]| peak_width / 2.0 + gs.len() as f64;
+    let f_dist_range = |(size, _): (usize, f64)| peak_width / 2.0 + size as f64;
 
     // distribute water from peaks evenly to either side, and correct for boundary effects:
     // (The problem has impermeable boundaries. When these are next to the ranges considered
@@ -155,8 +401,9 @@ fn water_distribution(
     let right_rain = f_rain(right_range);
 
     // check if well has enough space to hold water
-    let left_well_volume = well_volume(left_grounds, peak_heigth);
-    let right_well_volume = well_volume(right_grounds, peak_heigth);
+    let f_well_volume = |(size, land): (usize, f64)| peak_heigth as f64 * size as f64 - land;
+    let left_well_volume = f_well_volume(left_grounds);
+    let right_well_volume = f_well_volume(right_grounds);
 
     // if either side has not enough space to hold rain, distribute excees to the other side
     let mut left = left_rain;
@@ -172,144 +419,73 @@ fn water_distribution(
     return WaterDistribution { left, right };
 }
 
-fn recursor(pars: RecursorPars, grounds: &[u64], mut collector: Collector) -> Collector {
-    // destructure parameters
-    let RecursorPars {
-        water,
-        start,
-        end,
-        left_edge_peaks,
-        right_edge_peaks,
-        nest,
-    } = pars;
-
-    // find the highest peak
-    let peak_heigth = grounds.iter().max().unwrap();
-
-    // find position of peak in list
-    let i_peak: usize = grounds.iter().position(|x| x == peak_heigth).unwrap();
-    let absolute_peak = i_peak + start; // absoulte position of peak in collector vector
-
-    // see if adjacent segments right of the present one are at the same level
-    let n_adjacent_peaks = grounds[i_peak..]
-        .iter()
-        .take_while(|g| g == &peak_heigth)
-        .count();
-
-    // check if peak is at extremes of our range
-    let has_left = i_peak != 0;
-
-    // consider adjacent peaks for the right one
-    let has_right = i_peak + n_adjacent_peaks != grounds.len();
-
-    // peaks at the left boundary are a special condition for water distribution
-    // one recursion level down
-    let new_left_edge_peaks = if !has_left && n_adjacent_peaks > 0 {
-        n_adjacent_peaks
-    } else {
-        0
-    };
-    let new_right_edge_peaks = if !has_right && n_adjacent_peaks > 0 {
-        n_adjacent_peaks
-    } else {
-        0
-    };
-
-    // determine if peaks are under water
-    let displace = |gs: &[u64]| -> f64 { gs.iter().map(|g| *g as f64).sum() };
-    let underwater: bool = displace(grounds) + water > *peak_heigth as f64 * grounds.len() as f64;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::Rng;
+    use crate::zero::f64similar;
+
+    #[test]
+    fn cartesian_tree() {
+        let profile = Profile::new(&[3, 1, 6, 6, 4, 8]);
+        assert_eq!(profile.root, 5);
+        assert_eq!(profile.left[5], 2);
+        assert_eq!((profile.left[2], profile.right[2]), (0, 3));
+        assert_eq!((profile.left[3], profile.right[3]), (NONE, 4));
+        assert_eq!(profile.right[0], 1);
+        assert_eq!(profile.land(1, 4), 13.0);
+    }
 
-    // we are already done with this peak and its adjacent neighbours and
-    // can add it to collector
-    if underwater {
-        return collector;
-    } else {
-        for i in 0..n_adjacent_peaks {
-            collector.set_level(grounds[i + i_peak] as f64, i + absolute_peak);
+    #[test]
+    fn symmetric_mirror_invariance() {
+        let mut rng = Rng::new(1);
+        for _ in 0..1000 {
+            let size = rng.range(1, 40) as usize;
+            let grounds: Vec<u64> = (0..size).map(|_| rng.range(0, 9)).collect();
+            let duration = rng.range(0, 4);
+            let mirrored: Vec<u64> = grounds.iter().rev().copied().collect();
+
+            let received = symmetric(Problem::new(duration, &grounds));
+            let reflected = symmetric(Problem::new(duration, &mirrored));
+            let expected: Vec<f64> = reflected.levels.iter().rev().copied().collect();
+            assert_eq!(received.levels, expected);
+            assert!(f64similar(
+                received.water_tot,
+                (duration * size as u64) as f64
+            ));
         }
     }
 
-    // grounds left and right of peak
-    let grounds_left = &grounds[..i_peak];
-    let grounds_right = if has_right {
-        &grounds[i_peak + n_adjacent_peaks..]
-    } else {
-        &[]
-    };
-
-    // determine if the present range is ajacent to the right edge
-    let at_left_edge: bool = start == 0;
-    let at_right_edge: bool = end == collector.segments.len() - 1;
-
-    let WaterDistribution {
-        left: water_left,
-        right: water_right,
-    } = water_distribution(
-        water,
-        has_left,
-        has_right,
-        *peak_heigth,
-        n_adjacent_peaks as f64,
-        &grounds_left,
-        &grounds_right,
-        at_left_edge,
-        at_right_edge,
-        left_edge_peaks,
-        right_edge_peaks,
-    );
-
-    // try something here:
-    let new_level_left = (water_left + displace(grounds_left)) / grounds_left.len() as f64;
-    let new_level_right = (water_right + displace(grounds_right)) / grounds_right.len() as f64;
-
-    // check if there is world left left of the present peak
-    if has_left {
-        let grounds_left = &grounds[..i_peak];
-        let end_left = absolute_peak - 1;
-
-        // set water level to these segments
-        for i in start..end_left + 1 {
-            collector.set_level(new_level_left, i);
-            //println!("{:?}", collector.segments[i]);
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_equals_sequential() {
+        let mut rng = Rng::new(2);
+        for _ in 0..1000 {
+            let size = rng.range(1, 60) as usize;
+            let grounds: Vec<u64> = (0..size).map(|_| rng.range(0, 9)).collect();
+            let water = (rng.range(0, 3) * size as u64) as f64;
+            let expected = solve_pass(&grounds, water);
+            for cutoff in [1, 2, 5].iter() {
+                assert_eq!(solve_pass_parallel(&grounds, water, *cutoff), expected);
+            }
         }
 
-        // going into left recursion, not a tail call, but for most terrains
-        // this is much rarer than right recursions
-        let left_pars = RecursorPars::new(
-            water_left,
-            start,
-            end_left,
-            new_left_edge_peaks,
-            new_right_edge_peaks,
-            nest + 1,
-        );
-        collector = recursor(left_pars, &grounds_left, collector);
-    }
-
-    // END OF RECURSION
-    // if the present peak is already on the rightmost segment, we are done here
-    if absolute_peak + n_adjacent_peaks - 1 == end {
-        return collector;
+        let grounds: Vec<u64> = (0..100_000).map(|_| rng.range(0, 999)).collect();
+        let received = raise_parallel(Problem::new(3, &grounds));
+        let expected = raise(Problem::new(3, &grounds));
+        assert_eq!(received.levels, expected.levels);
     }
 
-    // going right, after first peak
-    let start_right = absolute_peak + n_adjacent_peaks;
-
-    // set present water level for segments right of peak
-    for i in start_right..end + 1 {
-        collector.set_level(new_level_right, i);
+    #[test]
+    fn long_profiles() {
+        // a ramp nests one sub problem per segment, a sawtooth has a peak
+        // every other segment
+        let size = 1_000_000;
+        let ramp: Vec<u64> = (0..size).collect();
+        let sawtooth: Vec<u64> = (0..size).map(|i| (i % 2) * 5).collect();
+        for grounds in [ramp, sawtooth].iter() {
+            let solution = raise(Problem::new(1, grounds));
+            assert!(f64similar(solution.water_tot, size as f64));
+        }
     }
-
-    let right_pars = RecursorPars::new(
-        water_right,
-        start_right,
-        end,
-        new_left_edge_peaks,
-        new_right_edge_peaks,
-        nest + 1,
-    );
-
-    // Tail Call  It would be quite interesting to know if tail call optimization works for
-    // this function. It seems to be quite a difficult topic in Rust.
-    return recursor(right_pars, &grounds_right, collector);
 }
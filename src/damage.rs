@@ -0,0 +1,259 @@
+// module damage puts a price on a flood. Every segment carries a damage
+// as a function of the depth of water on it: a value that is lost once the
+// segment is wet, or a curve of points (depth, damage). Over a solution the
+// damages add up to the total; over a sweep of rain durations they give
+// the damage as a function of rainfall.
+use crate::events;
+use crate::{Boundary, Error};
+
+// Damage of one segment as a function of the depth of water. It is checked
+// when it is built or read, so the damage at every depth is finite.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    any(feature = "serde", feature = "toml"),
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "RawDamage", into = "RawDamage")
+)]
+pub struct Damage {
+    kind: RawDamage,
+}
+
+// RawDamage is a damage as written in an input file
+// Value: all of it as soon as the segment is wet
+// Curve: linear from no damage at depth 0 to the first point and between
+// points, constant beyond the last
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    any(feature = "serde", feature = "toml"),
+    derive(serde::Serialize, serde::Deserialize),
+    serde(untagged)
+)]
+enum RawDamage {
+    Value(f64),
+    Curve(Vec<(f64, f64)>),
+}
+
+impl std::convert::TryFrom<RawDamage> for Damage {
+    type Error = Error;
+
+    fn try_from(raw: RawDamage) -> Result<Damage, Error> {
+        return match raw {
+            RawDamage::Value(value) => Damage::value(value),
+            RawDamage::Curve(points) => Damage::curve(points),
+        };
+    }
+}
+
+impl From<Damage> for RawDamage {
+    fn from(damage: Damage) -> RawDamage {
+        return damage.kind;
+    }
+}
+
+// Assessment of a flood, damage per segment and in total
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assessment {
+    pub segments: Vec<f64>,
+    pub total: f64,
+}
+
+impl Damage {
+    // value is lost as soon as the segment is wet, it must be finite
+    pub fn value(value: f64) -> Result<Damage, Error> {
+        if !value.is_finite() {
+            return Err(Error::InvalidInput("damages must be finite"));
+        }
+        return Ok(Damage {
+            kind: RawDamage::Value(value),
+        });
+    }
+
+    // curve takes points (depth, damage) of finite values and depths that
+    // increase from 0, so the damage between two points is always defined
+    pub fn curve(points: Vec<(f64, f64)>) -> Result<Damage, Error> {
+        let finite = points
+            .iter()
+            .all(|&(d, v)| d >= 0.0 && d.is_finite() && v.is_finite());
+        if !finite || !points.windows(2).all(|p| p[0].0 < p[1].0) {
+            return Err(Error::InvalidInput(
+                "damages must be finite, depths of curves increasing",
+            ));
+        }
+        return Ok(Damage {
+            kind: RawDamage::Curve(points),
+        });
+    }
+
+    // damage at a depth of water, a dry segment takes none
+    pub fn at(&self, depth: f64) -> f64 {
+        if depth <= crate::zero::EPSILON {
+            return 0.0;
+        }
+        let points = match &self.kind {
+            RawDamage::Value(value) => return *value,
+            RawDamage::Curve(points) => points,
+        };
+        let k = points.partition_point(|&(d, _)| d <= depth);
+        if k == points.len() {
+            return points.last().map_or(0.0, |&(_, damage)| damage);
+        }
+        let (d0, v0) = if k == 0 { (0.0, 0.0) } else { points[k - 1] };
+        let (d1, v1) = points[k];
+        return v0 + (v1 - v0) * (depth - d0) / (d1 - d0);
+    }
+}
+
+// assess prices the depths of water per segment, such as
+// Solution::water_covers, with one damage per segment
+pub fn assess(damages: &[Damage], depths: &[f64]) -> Result<Assessment, Error> {
+    if damages.len() != depths.len() {
+        return Err(Error::InvalidInput("one damage per segment"));
+    }
+    let segments: Vec<f64> = damages
+        .iter()
+        .zip(depths.iter())
+        .map(|(damage, &depth)| damage.at(depth))
+        .collect();
+    let total = segments.iter().sum();
+    return Ok(Assessment { segments, total });
+}
+
+// assess_widths prices entries that stand for a width of ground each, such
+// as the entries of a profile with x-coordinates. Damages are per unit of
+// width, the damage of an entry is weighted by its width.
+pub fn assess_widths(
+    damages: &[Damage],
+    depths: &[f64],
+    widths: &[f64],
+) -> Result<Assessment, Error> {
+    if widths.len() != depths.len() || widths.iter().any(|&w| !w.is_finite() || w < 0.0) {
+        return Err(Error::InvalidInput("widths must be finite, one per entry"));
+    }
+    let mut assessment = assess(damages, depths)?;
+    for (damage, width) in assessment.segments.iter_mut().zip(widths.iter()) {
+        *damage *= width;
+    }
+    assessment.total = assessment.segments.iter().sum();
+    return Ok(assessment);
+}
+
+// flood solves entries of the given widths for a duration between closed
+// walls, like events::levels_widths, and prices the water on them
+pub fn flood(
+    grounds: &[u64],
+    widths: &[f64],
+    damages: &[Damage],
+    duration: u64,
+) -> Result<Assessment, Error> {
+    let levels = events::levels_widths(grounds, widths, duration, Boundary::Closed)?;
+    let depths: Vec<f64> = levels
+        .iter()
+        .zip(grounds.iter())
+        .map(|(&level, &ground)| level - ground as f64)
+        .collect();
+    return assess_widths(damages, &depths, widths);
+}
+
+// sweep floods the entries for every duration and returns the total damage
+// of each: damage as a function of rainfall
+pub fn sweep(
+    grounds: &[u64],
+    widths: &[f64],
+    damages: &[Damage],
+    durations: &[u64],
+) -> Result<Vec<f64>, Error> {
+    let mut totals = Vec::with_capacity(durations.len());
+    for &duration in durations.iter() {
+        totals.push(flood(grounds, widths, damages, duration)?.total);
+    }
+    return Ok(totals);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solutions;
+    use crate::zero::f64similar;
+    use crate::Problem;
+
+    #[test]
+    fn curves() {
+        let house = Damage::curve(vec![(1.0, 50.0), (2.0, 80.0)]).unwrap();
+        assert_eq!(house.at(0.0), 0.0);
+        assert_eq!(house.at(0.5), 25.0);
+        assert_eq!(house.at(1.5), 65.0);
+        assert_eq!(house.at(7.0), 80.0);
+        let field = Damage::value(3.0).unwrap();
+        assert_eq!(field.at(0.0), 0.0);
+        assert_eq!(field.at(0.1), 3.0);
+        let step = Damage::curve(vec![(0.0, 4.0)]).unwrap();
+        assert_eq!(step.at(0.1), 4.0);
+        assert_eq!(Damage::curve(vec![]).unwrap().at(1.0), 0.0);
+
+        let received = assess(&[field.clone(), house], &[0.0, 1.5]).unwrap();
+        assert_eq!(received.segments, [0.0, 65.0]);
+        assert_eq!(received.total, 65.0);
+        assert!(assess(&[field], &[0.0, 1.0]).is_err());
+
+        // values must be finite, depths must increase, two points at one
+        // depth have no slope
+        assert!(Damage::value(f64::NAN).is_err());
+        assert!(Damage::value(f64::INFINITY).is_err());
+        assert!(Damage::curve(vec![(2.0, 1.0), (1.0, 2.0)]).is_err());
+        assert!(Damage::curve(vec![(1.0, 1.0), (1.0, 2.0)]).is_err());
+        assert!(Damage::curve(vec![(-1.0, 1.0)]).is_err());
+        assert!(Damage::curve(vec![(1.0, f64::INFINITY)]).is_err());
+
+        let damages = [
+            Damage::value(3.0).unwrap(),
+            Damage::curve(vec![(2.0, 80.0)]).unwrap(),
+        ];
+        let received = assess_widths(&damages, &[1.0, 1.0], &[2.0, 0.5]).unwrap();
+        assert_eq!(received.segments, [6.0, 20.0]);
+        assert_eq!(received.total, 26.0);
+        assert!(assess_widths(&damages, &[1.0, 1.0], &[2.0]).is_err());
+    }
+
+    #[test]
+    fn damage_and_rainfall() {
+        // one day fills the well of [5, 0, 0, 5] to 2, two days to 4, three
+        // days flood the peaks
+        let grounds = [5, 0, 0, 5];
+        let widths = [1.0; 4];
+        let damages = vec![
+            Damage::value(100.0).unwrap(),
+            Damage::curve(vec![(4.0, 40.0)]).unwrap(),
+            Damage::value(1.0).unwrap(),
+            Damage::value(100.0).unwrap(),
+        ];
+        let received = sweep(&grounds, &widths, &damages, &[0, 1, 2, 3]).unwrap();
+        assert!(f64similar(received[0], 0.0));
+        assert!(f64similar(received[1], 21.0));
+        assert!(f64similar(received[2], 41.0));
+        assert!(f64similar(received[3], 241.0));
+        assert!(received.windows(2).all(|t| t[0] <= t[1]));
+
+        // unit widths price the water covers of the profile solution
+        for duration in 0..5 {
+            let problem = Problem::new(duration, &grounds);
+            let solution = solutions::select_fn(&problem)(problem);
+            let expected = assess(&damages, &solution.water_covers).unwrap();
+            let received = flood(&grounds, &widths, &damages, duration).unwrap();
+            assert!(f64similar(received.total, expected.total));
+        }
+
+        // an entry of width 2 is two unit segments of the same damage
+        let split = [
+            damages[0].clone(),
+            damages[1].clone(),
+            damages[1].clone(),
+            damages[3].clone(),
+        ];
+        let wide = [damages[0].clone(), damages[1].clone(), damages[3].clone()];
+        for duration in 0..5 {
+            let expected = flood(&grounds, &widths, &split, duration).unwrap();
+            let received = flood(&[5, 0, 5], &[1.0, 2.0, 1.0], &wide, duration).unwrap();
+            assert!(f64similar(received.total, expected.total));
+        }
+    }
+}
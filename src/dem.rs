@@ -0,0 +1,227 @@
+// module dem reads digital elevation models in ESRI ASCII grid format
+// (.asc) and cuts transects out of them, which become profiles.
+//
+// A grid file has a header of keys and values, followed by the elevations
+// row by row from north to south:
+//     ncols 4
+//     nrows 3
+//     xllcorner 0.0
+//     yllcorner 0.0
+//     cellsize 10.0
+//     NODATA_value -9999
+//     ...
+// Cells are addressed as (row, column), counted from 0 at the top left.
+use std::error::Error;
+
+// Grid is an elevation model, values are stored row by row from the top
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    pub ncols: usize,
+    pub nrows: usize,
+    pub xll: f64,
+    pub yll: f64,
+    pub cellsize: f64,
+    pub nodata: Option<f64>,
+    pub values: Vec<f64>,
+}
+
+// Line is a transect through a grid: a row, a column, or a polyline
+// through cells, whose vertices are joined by straight lines of cells
+#[derive(Clone, Debug, PartialEq)]
+pub enum Line {
+    Row(usize),
+    Column(usize),
+    Polyline(Vec<(usize, usize)>),
+}
+
+// Datum maps profile heights back to elevations:
+// elevation = offset + height * resolution
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Datum {
+    pub offset: f64,
+    pub resolution: f64,
+}
+
+impl Grid {
+    pub fn parse(contents: &str) -> Result<Grid, Box<dyn Error>> {
+        let mut tokens = contents.split_whitespace().peekable();
+        let mut header = std::collections::HashMap::new();
+        while let Some(key) = tokens.peek() {
+            if !key.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                break;
+            }
+            let key = tokens.next().unwrap().to_ascii_lowercase();
+            let value: f64 = match tokens.next() {
+                Some(value) => value
+                    .parse()
+                    .map_err(|err| format!("header {} {}: {}", key, value, err))?,
+                None => return Err(format!("header {} has no value", key).into()),
+            };
+            header.insert(key, value);
+        }
+        let get = |key: &str| -> Result<f64, Box<dyn Error>> {
+            return match header.get(key) {
+                Some(&value) => Ok(value),
+                None => Err(format!("header misses {}", key).into()),
+            };
+        };
+
+        let ncols = get("ncols")? as usize;
+        let nrows = get("nrows")? as usize;
+        let cellsize = get("cellsize")?;
+        // corners and centres differ by half a cell
+        let xll = get("xllcorner").or_else(|_| get("xllcenter").map(|x| x - cellsize / 2.0))?;
+        let yll = get("yllcorner").or_else(|_| get("yllcenter").map(|y| y - cellsize / 2.0))?;
+        let nodata = header.get("nodata_value").copied();
+
+        let values = tokens
+            .map(|t| t.parse().map_err(|err| format!("value {}: {}", t, err)))
+            .collect::<Result<Vec<f64>, String>>()?;
+        if values.len() != ncols * nrows {
+            let message = format!("expected {} values, found {}", ncols * nrows, values.len());
+            return Err(message.into());
+        }
+        return Ok(Grid {
+            ncols,
+            nrows,
+            xll,
+            yll,
+            cellsize,
+            nodata,
+            values,
+        });
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        return self.values[row * self.ncols + col];
+    }
+
+    // cells lists the cells along a line in order, without repetitions at
+    // the vertices of a polyline
+    pub fn cells(&self, line: &Line) -> Result<Vec<(usize, usize)>, Box<dyn Error>> {
+        let cells: Vec<(usize, usize)> = match line {
+            Line::Row(r) => (0..self.ncols).map(|c| (*r, c)).collect(),
+            Line::Column(c) => (0..self.nrows).map(|r| (r, *c)).collect(),
+            Line::Polyline(vertices) => {
+                let mut cells = vertices.iter().take(1).copied().collect::<Vec<_>>();
+                for pair in vertices.windows(2) {
+                    cells.extend(straight(pair[0], pair[1]).into_iter().skip(1));
+                }
+                cells
+            }
+        };
+        if cells.is_empty() {
+            return Err("transect is empty".into());
+        }
+        if let Some(&(r, c)) = cells
+            .iter()
+            .find(|&&(r, c)| r >= self.nrows || c >= self.ncols)
+        {
+            return Err(format!("cell ({}, {}) is outside of the grid", r, c).into());
+        }
+        return Ok(cells);
+    }
+
+    // transect returns the elevations along a line, it fails on cells
+    // without data
+    pub fn transect(&self, line: &Line) -> Result<Vec<f64>, Box<dyn Error>> {
+        let mut elevations = Vec::new();
+        for (r, c) in self.cells(line)? {
+            let value = self.get(r, c);
+            if Some(value) == self.nodata || !value.is_finite() {
+                return Err(format!("cell ({}, {}) has no data", r, c).into());
+            }
+            elevations.push(value);
+        }
+        return Ok(elevations);
+    }
+}
+
+// to_profile turns elevations into integer heights in units of resolution
+// above the lowest elevation, rounded to the nearest unit
+pub fn to_profile(elevations: &[f64], resolution: f64) -> (Vec<u64>, Datum) {
+    let offset = elevations.iter().cloned().fold(f64::INFINITY, f64::min);
+    let profile = elevations
+        .iter()
+        .map(|e| ((e - offset) / resolution).round() as u64)
+        .collect();
+    return (profile, Datum { offset, resolution });
+}
+
+impl Datum {
+    pub fn elevation(&self, height: f64) -> f64 {
+        return self.offset + height * self.resolution;
+    }
+}
+
+// straight returns the cells of a line from a to b, both included, every
+// step goes to one of the eight neighbours (Bresenham)
+fn straight(a: (usize, usize), b: (usize, usize)) -> Vec<(usize, usize)> {
+    let (r0, c0) = (a.0 as i64, a.1 as i64);
+    let (r1, c1) = (b.0 as i64, b.1 as i64);
+    let (dr, dc) = ((r1 - r0).abs(), -(c1 - c0).abs());
+    let (sr, sc) = ((r1 - r0).signum(), (c1 - c0).signum());
+    let (mut r, mut c, mut err) = (r0, c0, dr + dc);
+    let mut cells = vec![(r as usize, c as usize)];
+    while (r, c) != (r1, c1) {
+        let e2 = 2 * err;
+        if e2 >= dc {
+            err += dc;
+            r += sr;
+        }
+        if e2 <= dr {
+            err += dr;
+            c += sc;
+        }
+        cells.push((r as usize, c as usize));
+    }
+    return cells;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID: &str = "ncols 4
+        nrows 3
+        xllcorner 100.0
+        yllcenter 200.0
+        cellsize 10.0
+        NODATA_value -9999
+        5.0 1.5 3.0 9.0
+        2.0 0.5 -9999 8.0
+        7.0 6.0 4.0 3.5";
+
+    #[test]
+    fn parse_grid() {
+        let grid = Grid::parse(GRID).unwrap();
+        assert_eq!((grid.nrows, grid.ncols), (3, 4));
+        assert_eq!((grid.xll, grid.yll), (100.0, 195.0));
+        assert_eq!(grid.nodata, Some(-9999.0));
+        assert_eq!(grid.get(2, 1), 6.0);
+        assert!(
+            Grid::parse("ncols 2\nnrows 2\nxllcorner 0\nyllcorner 0\ncellsize 1\n1 2 3").is_err()
+        );
+    }
+
+    #[test]
+    fn transects() {
+        let grid = Grid::parse(GRID).unwrap();
+        assert_eq!(grid.transect(&Line::Row(0)).unwrap(), [5.0, 1.5, 3.0, 9.0]);
+        assert_eq!(grid.transect(&Line::Column(3)).unwrap(), [9.0, 8.0, 3.5]);
+        assert!(grid.transect(&Line::Row(1)).is_err());
+        assert!(grid.transect(&Line::Column(4)).is_err());
+
+        let line = Line::Polyline(vec![(0, 0), (2, 2), (2, 3)]);
+        assert_eq!(grid.cells(&line).unwrap(), [(0, 0), (1, 1), (2, 2), (2, 3)]);
+        assert_eq!(grid.transect(&line).unwrap(), [5.0, 0.5, 4.0, 3.5]);
+        assert_eq!(straight((0, 3), (2, 0)), [(0, 3), (1, 2), (1, 1), (2, 0)]);
+    }
+
+    #[test]
+    fn profile_and_datum() {
+        let (profile, datum) = to_profile(&[5.0, 0.5, 4.0, 3.5], 0.5);
+        assert_eq!(profile, [9, 0, 7, 6]);
+        assert_eq!(datum.elevation(7.0), 4.0);
+    }
+}
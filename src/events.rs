@@ -0,0 +1,753 @@
+// module events follows the water as the rain goes on and lists the moments
+// at which the topology of the lakes changes. It generalises the single
+// threshold saturation_water in Problem to the whole merge tree.
+//
+// The simulation is a fill-and-spill model on tree::MergeTree. Rain on dry
+// land runs downhill into the next lake, rain on a peak is shared evenly by
+// both sides. A lake fills until it reaches the lowest peak next to it, then
+// spills its inflow over that peak. Full neighbouring lakes form a pool,
+// whose inflow goes in equal parts to the lakes left and right of the pool.
+// Once all children of a node are full, they join into its lake.
+//
+// Between two events every lake fills at a constant rate, the simulation
+// jumps from event to event.
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::tree::MergeTree;
+use crate::wells::Side;
+use crate::{Boundary, Error};
+
+// Kind of topology change
+// Spill: a lake is full and starts to spill over the peak(s) at its side
+// Merge: the peaks between two or more lakes submerge and they join
+// Submerge: a lake rises over a peak that has no other lake behind it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Spill(Side),
+    Merge,
+    Submerge,
+}
+
+// Event records a topology change
+// duration: days of rain until it happens
+// water: total amount of rain until it happens
+// start, end, level: range and water level of the lake concerned
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub duration: f64,
+    pub water: f64,
+    pub kind: Kind,
+    pub start: usize,
+    pub end: usize,
+    pub level: u64,
+}
+
+// thresholds lists all topology changes of a profile in order of rain
+// duration. The last event is the lake over the whole profile reaching
+// saturation, at saturation_water.
+pub fn thresholds(grounds: &[u64]) -> Vec<Event> {
+    let tree = MergeTree::new(grounds);
+    let rain = vec![1.0; grounds.len()];
+    let capacities = capacities(&tree);
+    let mut engine = Engine::new(&tree, &rain, capacities, false);
+    engine.advance(f64::INFINITY);
+
+    let total_rain = grounds.len() as f64;
+    return engine
+        .log
+        .iter()
+        .map(|&(duration, node, kind)| {
+            let lake = &tree.nodes[node];
+            let level = match kind {
+                Kind::Spill(_) => tree.nodes[lake.parent.unwrap()].level,
+                _ => lake.level,
+            };
+            Event {
+                duration,
+                water: duration * total_rain,
+                kind,
+                start: lake.start,
+                end: lake.end,
+                level,
+            }
+        })
+        .collect();
+}
+
+// levels of flat-topped segments after duration days of rain, where rain
+// is the rate of water falling on each segment per day
+pub(crate) fn levels_at(grounds: &[u64], rain: &[f64], duration: f64) -> Vec<f64> {
+    let tree = MergeTree::new(grounds);
+    let mut engine = Engine::new(&tree, rain, capacities(&tree), false);
+    engine.advance(duration);
+    return levels_of(&tree, &engine, grounds, duration);
+}
+
+// levels with open boundaries: water spilling over the outermost peaks runs
+// off. The profile is lifted by one and gets a sink at either end, a lake
+// of infinite capacity below all ground that receives no rain.
+pub(crate) fn levels_open(grounds: &[u64], duration: f64) -> Vec<f64> {
+    let size = grounds.len();
+    let mut padded = Vec::with_capacity(size + 2);
+    padded.push(0);
+    padded.extend(grounds.iter().map(|&g| g + 1));
+    padded.push(0);
+    let mut rain = vec![1.0; size + 2];
+    rain[0] = 0.0;
+    rain[size + 1] = 0.0;
+
+    let tree = MergeTree::new(&padded);
+    let mut capacity = capacities(&tree);
+    for (id, node) in tree.nodes.iter().enumerate() {
+        if node.children.is_empty() && (node.start == 0 || node.end == size + 1) {
+            capacity[id] = f64::INFINITY;
+        }
+    }
+    let mut engine = Engine::new(&tree, &rain, capacity, false);
+    engine.advance(duration);
+
+    let levels = levels_of(&tree, &engine, &padded, duration);
+    return levels[1..=size].iter().map(|l| l - 1.0).collect();
+}
+
+// levels_widths floods entries of any positive width, such as segments
+// between x-coordinates, with duration days of rain on each unit of width.
+// Adjacent entries may have the same height. With unit widths the levels
+// are those of levels_at, or of levels_open with open boundaries.
+pub fn levels_widths(
+    heights: &[u64],
+    widths: &[f64],
+    duration: u64,
+    boundary: Boundary,
+) -> Result<Vec<f64>, Error> {
+    let size = heights.len();
+    if size == 0 {
+        return Err(Error::EmptyProfile);
+    }
+    if widths.len() != size || widths.iter().any(|&w| !w.is_finite() || w <= 0.0) {
+        return Err(Error::InvalidInput(
+            "widths must be positive, one per entry",
+        ));
+    }
+    if boundary == Boundary::Closed {
+        return Ok(weighted(heights, widths, widths, duration as f64, false));
+    }
+
+    // lifted by one between two sinks without rain, like levels_open
+    if heights.contains(&u64::MAX) {
+        return Err(Error::Overflow);
+    }
+    let mut padded = Vec::with_capacity(size + 2);
+    padded.push(0);
+    padded.extend(heights.iter().map(|&h| h + 1));
+    padded.push(0);
+    let mut padded_widths = vec![1.0];
+    padded_widths.extend(widths.iter());
+    padded_widths.push(1.0);
+    let mut rain = padded_widths.clone();
+    rain[0] = 0.0;
+    rain[size + 1] = 0.0;
+    let levels = weighted(&padded, &padded_widths, &rain, duration as f64, true);
+    return Ok(levels[1..=size].iter().map(|l| l - 1.0).collect());
+}
+
+// weighted levels of entries of the given widths after duration days of
+// rain, rain per entry. With sinks the outermost leaves never fill.
+fn weighted(heights: &[u64], widths: &[f64], rain: &[f64], duration: f64, sinks: bool) -> Vec<f64> {
+    let tree = MergeTree::new(heights);
+    let mut prefix = vec![(0.0, 0.0); heights.len() + 1];
+    for (i, (&h, &w)) in heights.iter().zip(widths.iter()).enumerate() {
+        prefix[i + 1] = (prefix[i].0 + w, prefix[i].1 + h as f64 * w);
+    }
+    // width and volume of the ground of a node
+    let ground = |start: usize, end: usize| {
+        return (
+            prefix[end + 1].0 - prefix[start].0,
+            prefix[end + 1].1 - prefix[start].1,
+        );
+    };
+
+    let last = heights.len() - 1;
+    let capacity = tree
+        .nodes
+        .iter()
+        .map(|node| {
+            let sink = sinks && node.children.is_empty() && (node.start == 0 || node.end == last);
+            let (width, land) = ground(node.start, node.end);
+            match node.parent {
+                Some(parent) if !sink => tree.nodes[parent].level as f64 * width - land,
+                _ => f64::INFINITY,
+            }
+        })
+        .collect();
+    let mut engine = Engine::new(&tree, rain, capacity, false);
+    engine.advance(duration);
+
+    let mut levels: Vec<f64> = heights.iter().map(|&h| h as f64).collect();
+    for (id, node) in tree.nodes.iter().enumerate() {
+        let level = match engine.state[id] {
+            State::Filling => {
+                let (width, land) = ground(node.start, node.end);
+                (engine.volume_now(id, duration) + land) / width
+            }
+            State::Full => tree.nodes[node.parent.unwrap()].level as f64,
+            _ => continue,
+        };
+        for l in levels[node.start..=node.end].iter_mut() {
+            *l = level;
+        }
+    }
+    return levels;
+}
+
+// levels_of reads the levels of all lakes from a simulation at a time
+fn levels_of(tree: &MergeTree, engine: &Engine, grounds: &[u64], time: f64) -> Vec<f64> {
+    let mut levels: Vec<f64> = grounds.iter().map(|&g| g as f64).collect();
+    for (id, node) in tree.nodes.iter().enumerate() {
+        let level = match engine.state[id] {
+            State::Filling => {
+                let width = (node.end - node.start + 1) as f64;
+                let land: f64 = grounds[node.start..=node.end]
+                    .iter()
+                    .map(|&g| g as f64)
+                    .sum();
+                (engine.volume_now(id, time) + land) / width
+            }
+            State::Full => tree.nodes[node.parent.unwrap()].level as f64,
+            _ => continue,
+        };
+        for l in levels[node.start..=node.end].iter_mut() {
+            *l = level;
+        }
+    }
+    return levels;
+}
+
+// capacities of flat-topped segments: a lake is full when it reaches the
+// level of its parent, the root never is
+pub(crate) fn capacities(tree: &MergeTree) -> Vec<f64> {
+    return tree
+        .nodes
+        .iter()
+        .map(|node| match node.parent {
+            Some(parent) => {
+                let depth = (tree.nodes[parent].level - node.level) as f64;
+                node.volume + depth * (node.end - node.start + 1) as f64
+            }
+            None => f64::INFINITY,
+        })
+        .collect();
+}
+
+// state of a node in the simulation
+// Dry: its children are still separate lakes
+// Filling: it is a lake whose level rises
+// Full: it is a lake at the level of its parent and passes its inflow on
+// Merged: it has joined its siblings in the lake of its parent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum State {
+    Dry,
+    Filling,
+    Full,
+    Merged,
+}
+
+// Piece of the history of a lake: from time on its volume grows by rate,
+// full lakes stay at the level of their parent
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Piece {
+    pub(crate) time: f64,
+    pub(crate) volume: f64,
+    pub(crate) rate: f64,
+    pub(crate) full: bool,
+}
+
+// Pending is a scheduled fill of a lake, invalid once the version of the
+// node changed. Ordered such that the heap pops the earliest first.
+#[derive(Debug)]
+struct Pending {
+    time: f64,
+    node: usize,
+    version: u64,
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        return self.cmp(other) == Ordering::Equal;
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        return other
+            .time
+            .total_cmp(&self.time)
+            .then(other.node.cmp(&self.node));
+    }
+}
+
+// Engine runs the simulation on a merge tree. Rain is given per segment as
+// rate of water per day, capacities per node as the volume at which it is
+// full. Volumes are stored at the time of their last change. If a trace is
+// kept, it holds the history of every lake as a list of pieces.
+pub(crate) struct Engine<'a> {
+    tree: &'a MergeTree,
+    capacity: Vec<f64>,
+    pub(crate) state: Vec<State>,
+    volume: Vec<f64>,
+    stamp: Vec<f64>,
+    rate: Vec<f64>,
+    position: Vec<usize>,
+    full_children: Vec<usize>,
+    // pools of full siblings as disjoint sets, bounds and surplus are kept
+    // at the representative
+    pool: Vec<usize>,
+    pool_bounds: Vec<(usize, usize)>,
+    surplus: Vec<f64>,
+    queue: BinaryHeap<Pending>,
+    version: Vec<u64>,
+    pub(crate) time: f64,
+    pub(crate) log: Vec<(f64, usize, Kind)>,
+    pub(crate) trace: Option<Vec<Vec<Piece>>>,
+}
+
+impl<'a> Engine<'a> {
+    pub(crate) fn new(tree: &'a MergeTree, rain: &[f64], capacity: Vec<f64>, trace: bool) -> Self {
+        let size = tree.nodes.len();
+        let mut position = vec![0; size];
+        for node in tree.nodes.iter() {
+            for (j, &child) in node.children.iter().enumerate() {
+                position[child] = j;
+            }
+        }
+
+        let mut engine = Engine {
+            tree,
+            capacity,
+            state: vec![State::Dry; size],
+            volume: vec![0.0; size],
+            stamp: vec![0.0; size],
+            rate: vec![0.0; size],
+            position,
+            full_children: vec![0; size],
+            pool: (0..size).collect(),
+            pool_bounds: vec![(0, 0); size],
+            surplus: vec![0.0; size],
+            queue: BinaryHeap::new(),
+            version: vec![0; size],
+            time: 0.0,
+            log: Vec::new(),
+            trace: if trace {
+                Some(vec![vec![]; size])
+            } else {
+                None
+            },
+        };
+        engine.initial_rates(rain);
+        return engine;
+    }
+
+    // route the rain top down: rain on the dry highest segments of a node
+    // runs into its children, rain entering a node at one side runs into
+    // its outermost child at that side, down to the leaves
+    fn initial_rates(&mut self, rain: &[f64]) {
+        let tree = self.tree;
+        let mut prefix = vec![0.0; rain.len() + 1];
+        for (i, r) in rain.iter().enumerate() {
+            prefix[i + 1] = prefix[i] + r;
+        }
+        let rain_on = |start: usize, end: usize| prefix[end + 1] - prefix[start];
+
+        let mut from_left = vec![0.0; tree.nodes.len()];
+        let mut from_right = vec![0.0; tree.nodes.len()];
+        let mut stack = vec![tree.root];
+        while let Some(a) = stack.pop() {
+            let node = &tree.nodes[a];
+            if node.children.is_empty() {
+                self.state[a] = State::Filling;
+                self.rate[a] = rain_on(node.start, node.end) + from_left[a] + from_right[a];
+                self.schedule(a);
+                self.record(a);
+                continue;
+            }
+
+            let first = node.children[0];
+            let last = *node.children.last().unwrap();
+            from_left[first] += from_left[a];
+            if tree.nodes[first].start > node.start {
+                from_left[first] += rain_on(node.start, tree.nodes[first].start - 1);
+            }
+            for pair in node.children.windows(2) {
+                let shared = rain_on(tree.nodes[pair[0]].end + 1, tree.nodes[pair[1]].start - 1);
+                from_right[pair[0]] += shared / 2.0;
+                from_left[pair[1]] += shared / 2.0;
+            }
+            from_right[last] += from_right[a];
+            if tree.nodes[last].end < node.end {
+                from_right[last] += rain_on(tree.nodes[last].end + 1, node.end);
+            }
+            stack.extend(node.children.iter());
+        }
+    }
+
+    // volume of a lake at the present time
+    pub(crate) fn volume_now(&self, node: usize, time: f64) -> f64 {
+        return match self.state[node] {
+            State::Filling => self.volume[node] + self.rate[node] * (time - self.stamp[node]),
+            _ => self.volume[node],
+        };
+    }
+
+    // bring the volume of a lake up to date before its rate changes
+    fn settle(&mut self, node: usize) {
+        self.volume[node] = self.volume_now(node, self.time);
+        self.stamp[node] = self.time;
+    }
+
+    fn record(&mut self, node: usize) {
+        let piece = Piece {
+            time: self.time,
+            volume: self.volume[node],
+            rate: self.rate[node],
+            full: self.state[node] == State::Full,
+        };
+        if let Some(trace) = self.trace.as_mut() {
+            trace[node].push(piece);
+        }
+    }
+
+    fn schedule(&mut self, node: usize) {
+        self.version[node] += 1;
+        let room = self.capacity[node] - self.volume[node];
+        if !room.is_finite() || self.rate[node] <= 0.0 {
+            return;
+        }
+        let time = self.time + (room / self.rate[node]).max(0.0);
+        self.queue.push(Pending {
+            time,
+            node,
+            version: self.version[node],
+        });
+    }
+
+    // run all events up to and including time until
+    pub(crate) fn advance(&mut self, until: f64) {
+        while let Some(next) = self.queue.peek() {
+            if next.time > until {
+                break;
+            }
+            let Pending {
+                time,
+                node,
+                version,
+            } = self.queue.pop().unwrap();
+            if version != self.version[node] || self.state[node] != State::Filling {
+                continue;
+            }
+            self.time = time;
+            self.fill(node);
+        }
+    }
+
+    fn find(&mut self, node: usize) -> usize {
+        let mut root = node;
+        while self.pool[root] != root {
+            root = self.pool[root];
+        }
+        let mut node = node;
+        while self.pool[node] != root {
+            let next = self.pool[node];
+            self.pool[node] = root;
+            node = next;
+        }
+        return root;
+    }
+
+    // share of the surplus of a pool that goes to its left and right
+    // neighbour, all of it if there is only one
+    fn shares(&self, representative: usize, siblings: usize) -> (f64, f64) {
+        let (lo, hi) = self.pool_bounds[representative];
+        let surplus = self.surplus[representative];
+        return match (lo > 0, hi + 1 < siblings) {
+            (true, true) => (surplus / 2.0, surplus / 2.0),
+            (true, false) => (surplus, 0.0),
+            (false, true) => (0.0, surplus),
+            (false, false) => (0.0, 0.0),
+        };
+    }
+
+    // a lake reached its capacity
+    fn fill(&mut self, node: usize) {
+        self.settle(node);
+        self.volume[node] = self.capacity[node];
+        self.state[node] = State::Full;
+        self.record(node);
+
+        let tree = self.tree;
+        let parent = tree.nodes[node].parent.unwrap();
+        let siblings = &tree.nodes[parent].children;
+        let k = siblings.len();
+        let j = self.position[node];
+        self.full_children[parent] += 1;
+
+        let left_pool = if j > 0 && self.state[siblings[j - 1]] == State::Full {
+            Some(self.find(siblings[j - 1]))
+        } else {
+            None
+        };
+        let right_pool = if j + 1 < k && self.state[siblings[j + 1]] == State::Full {
+            Some(self.find(siblings[j + 1]))
+        } else {
+            None
+        };
+
+        // what the neighbouring pools gave to this lake and to their far side
+        let (to_far_left, from_left) = left_pool.map_or((0.0, 0.0), |p| self.shares(p, k));
+        let (from_right, to_far_right) = right_pool.map_or((0.0, 0.0), |p| self.shares(p, k));
+        let own = self.rate[node] - from_left - from_right;
+        self.rate[node] = own;
+
+        // join this lake and the neighbouring pools into one pool
+        let lo = left_pool.map_or(j, |p| self.pool_bounds[p].0);
+        let hi = right_pool.map_or(j, |p| self.pool_bounds[p].1);
+        let surplus = own
+            + left_pool.map_or(0.0, |p| self.surplus[p])
+            + right_pool.map_or(0.0, |p| self.surplus[p]);
+        for p in [left_pool, right_pool].iter().flatten() {
+            self.pool[*p] = node;
+        }
+        self.pool[node] = node;
+        self.pool_bounds[node] = (lo, hi);
+        self.surplus[node] = surplus;
+
+        if self.full_children[parent] == k {
+            self.activate(parent, surplus);
+            return;
+        }
+
+        let outlets = (
+            tree.nodes[node].start > tree.nodes[parent].start,
+            tree.nodes[node].end < tree.nodes[parent].end,
+        );
+        let side = match outlets {
+            (true, true) => Side::Both,
+            (true, false) => Side::Left,
+            _ => Side::Right,
+        };
+        self.log.push((self.time, node, Kind::Spill(side)));
+
+        let (to_left, to_right) = self.shares(node, k);
+        if lo > 0 {
+            self.add_inflow(siblings[lo - 1], false, to_left - to_far_left);
+        }
+        if hi + 1 < k {
+            self.add_inflow(siblings[hi + 1], true, to_right - to_far_right);
+        }
+    }
+
+    // all children of a node are full, they join into its lake
+    fn activate(&mut self, node: usize, inflow: f64) {
+        let tree = self.tree;
+        let mut volume = 0.0;
+        for &child in tree.nodes[node].children.iter() {
+            self.state[child] = State::Merged;
+            volume += self.capacity[child];
+        }
+        self.state[node] = State::Filling;
+        self.volume[node] = volume;
+        self.stamp[node] = self.time;
+        self.rate[node] = inflow;
+        self.schedule(node);
+        self.record(node);
+
+        let kind = if tree.nodes[node].children.len() > 1 {
+            Kind::Merge
+        } else {
+            Kind::Submerge
+        };
+        self.log.push((self.time, node, kind));
+    }
+
+    // change the inflow into a node entering from the left or right. The
+    // water runs down into the outermost lake at that side; when that lake
+    // is full it passes the change on to the neighbours of its pool.
+    fn add_inflow(&mut self, node: usize, from_left: bool, delta: f64) {
+        let tree = self.tree;
+        let mut work = vec![(node, from_left, delta)];
+        while let Some((mut node, from_left, delta)) = work.pop() {
+            while self.state[node] == State::Dry {
+                let children = &tree.nodes[node].children;
+                node = if from_left {
+                    children[0]
+                } else {
+                    *children.last().unwrap()
+                };
+            }
+
+            match self.state[node] {
+                State::Filling => {
+                    self.settle(node);
+                    self.rate[node] += delta;
+                    self.schedule(node);
+                    self.record(node);
+                }
+                State::Full => {
+                    self.rate[node] += delta;
+                    let representative = self.find(node);
+                    let parent = tree.nodes[node].parent.unwrap();
+                    let siblings = &tree.nodes[parent].children;
+                    let (before_left, before_right) = self.shares(representative, siblings.len());
+                    self.surplus[representative] += delta;
+                    let (after_left, after_right) = self.shares(representative, siblings.len());
+                    let (lo, hi) = self.pool_bounds[representative];
+                    if lo > 0 {
+                        work.push((siblings[lo - 1], false, after_left - before_left));
+                    }
+                    if hi + 1 < siblings.len() {
+                        work.push((siblings[hi + 1], true, after_right - before_right));
+                    }
+                }
+                _ => unreachable!("water runs into lakes only"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zero::{f64similar, vecf64similar};
+
+    #[test]
+    fn single_well() {
+        // four segments of rain fill the well up to the peaks at 2
+        let received = thresholds(&[2, 0, 0, 2]);
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].kind, Kind::Submerge);
+        assert!(f64similar(received[0].duration, 1.0));
+        assert!(f64similar(received[0].water, 4.0));
+    }
+
+    #[test]
+    fn spill_then_merge() {
+        let received = thresholds(&[3, 1, 6, 4, 8, 9]);
+        let kinds: Vec<Kind> = received.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                Kind::Spill(Side::Left),
+                Kind::Submerge,
+                Kind::Merge,
+                Kind::Submerge,
+                Kind::Submerge,
+            ]
+        );
+
+        // the well right of the 6 gets 3.5 segments of rain and holds 2
+        assert!(f64similar(received[0].duration, 2.0 / 3.5));
+        assert_eq!(received[0].level, 6);
+        assert_eq!((received[0].start, received[0].end), (3, 3));
+
+        // then the left well fills from both sides
+        assert!(f64similar(received[1].duration, 2.0 / 3.0));
+        assert_eq!(received[1].level, 3);
+
+        // the last event is saturation
+        let last = received.last().unwrap();
+        assert!(f64similar(last.water, 23.0));
+        for pair in received.windows(2) {
+            assert!(pair[0].duration <= pair[1].duration);
+        }
+    }
+
+    #[test]
+    fn open_boundaries() {
+        // the well holds 3, gets 2 per day and spills off both ends when full
+        assert!(vecf64similar(
+            &levels_open(&[3, 0, 3], 1.0),
+            &[3.0, 2.0, 3.0]
+        ));
+        assert!(vecf64similar(
+            &levels_open(&[3, 0, 3], 10.0),
+            &[3.0, 3.0, 3.0]
+        ));
+        // nothing stays on a slope
+        assert!(vecf64similar(
+            &levels_open(&[1, 2, 3], 5.0),
+            &[1.0, 2.0, 3.0]
+        ));
+    }
+
+    #[test]
+    fn equal_peaks() {
+        let received = thresholds(&[0, 5, 0, 5, 0, 5, 0]);
+        // the inner wells get more rain and are full first, both at once
+        assert_eq!(received[0].kind, Kind::Spill(Side::Both));
+        assert_eq!(received[1].kind, Kind::Spill(Side::Both));
+        assert!(f64similar(received[0].duration, 2.5));
+        assert_eq!(received.last().unwrap().kind, Kind::Merge);
+        assert!(f64similar(received.last().unwrap().water, 20.0));
+    }
+
+    #[test]
+    fn weighted_entries() {
+        // all profiles of up to five segments of heights 0 to 3 with unit
+        // widths, and with every entry twice as wide as two equal segments
+        for size in 1..=5u32 {
+            for code in 0..4u64.pow(size) {
+                let grounds: Vec<u64> = (0..size).map(|k| code / 4u64.pow(k) % 4).collect();
+                let doubled: Vec<u64> = grounds.iter().flat_map(|&g| [g, g]).collect();
+                let units = vec![1.0; grounds.len()];
+                let twos = vec![2.0; grounds.len()];
+                for duration in 0..4 {
+                    let closed = levels_widths(&grounds, &units, duration, Boundary::Closed);
+                    let expected = levels_at(&grounds, &units, duration as f64);
+                    assert!(vecf64similar(&closed.unwrap(), &expected));
+                    let open = levels_widths(&grounds, &units, duration, Boundary::Open);
+                    let expected = levels_open(&grounds, duration as f64);
+                    assert!(vecf64similar(&open.unwrap(), &expected));
+
+                    let wide = levels_widths(&grounds, &twos, duration, Boundary::Closed).unwrap();
+                    let expected = levels_at(&doubled, &vec![1.0; doubled.len()], duration as f64);
+                    let expanded: Vec<f64> = wide.iter().flat_map(|&l| [l, l]).collect();
+                    assert!(vecf64similar(&expanded, &expected));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fractional_widths() {
+        // a well of width 0.5 between walls of width 0.25 holds all rain
+        let received = levels_widths(&[4, 0, 4], &[0.25, 0.5, 0.25], 1, Boundary::Closed);
+        assert_eq!(received.unwrap(), [4.0, 2.0, 4.0]);
+
+        // scaling all widths leaves the levels
+        let heights = [3, 1, 6, 4, 4, 8, 9];
+        let widths = [1.0, 2.0, 1.0, 1.0, 2.0, 1.0, 3.0];
+        let halves: Vec<f64> = widths.iter().map(|w| w / 2.0).collect();
+        let a = levels_widths(&heights, &widths, 2, Boundary::Closed).unwrap();
+        let b = levels_widths(&heights, &halves, 2, Boundary::Closed).unwrap();
+        assert!(vecf64similar(&a, &b));
+
+        assert_eq!(
+            levels_widths(&[], &[], 1, Boundary::Closed),
+            Err(Error::EmptyProfile)
+        );
+        assert!(levels_widths(&[1, 2], &[1.0, 0.0], 1, Boundary::Closed).is_err());
+        assert!(levels_widths(&[1, 2], &[1.0], 1, Boundary::Open).is_err());
+        assert_eq!(
+            levels_widths(&[u64::MAX], &[1.0], 1, Boundary::Open),
+            Err(Error::Overflow)
+        );
+    }
+}
@@ -0,0 +1,157 @@
+// module ffi is a C interface to solve. Heights are passed as pointer and
+// length, results are written into buffers of the caller. Instead of the
+// panics of Problem::new the functions return status codes, and a panic
+// does not unwind into C: it is caught and returned as RAIN_PANIC. The
+// header include/rain.h is generated from this file with cbindgen.
+use std::os::raw::c_char;
+use std::panic::{self, UnwindSafe};
+use std::slice;
+
+use crate::{solve, Error, Options};
+
+// status codes
+pub const RAIN_OK: i32 = 0;
+pub const RAIN_NULL_POINTER: i32 = 1;
+pub const RAIN_EMPTY_PROFILE: i32 = 2;
+pub const RAIN_OVERFLOW: i32 = 3;
+pub const RAIN_NOT_CONSERVED: i32 = 4;
+pub const RAIN_UNSUPPORTED: i32 = 5;
+pub const RAIN_PANIC: i32 = 6;
+pub const RAIN_INVALID_INPUT: i32 = 7;
+
+/// Solves `days` of rain on `len` segments with the given `heights`.
+///
+/// Writes the levels of water or ground into `levels` and the depth of
+/// water into `water_covers`, both must hold `len` values. `water_covers`
+/// may be NULL if it is not needed. Returns `RAIN_OK` or an error code,
+/// the buffers are left untouched on error. An internal error returns
+/// `RAIN_PANIC` instead of unwinding into the caller.
+///
+/// # Safety
+/// `heights` must point to `len` readable values, `levels` and, unless
+/// NULL, `water_covers` to `len` writable ones.
+#[no_mangle]
+pub unsafe extern "C" fn rain_solve(
+    heights: *const u64,
+    len: usize,
+    days: u64,
+    levels: *mut f64,
+    water_covers: *mut f64,
+) -> i32 {
+    return guarded(RAIN_PANIC, || {
+        if heights.is_null() || levels.is_null() {
+            return RAIN_NULL_POINTER;
+        }
+        if len == 0 {
+            return RAIN_EMPTY_PROFILE;
+        }
+        let profile = slice::from_raw_parts(heights, len);
+        let solution = match solve(profile, days, &Options::default()) {
+            Ok(solution) => solution,
+            Err(err) => return status(&err),
+        };
+
+        slice::from_raw_parts_mut(levels, len).copy_from_slice(&solution.levels);
+        if !water_covers.is_null() {
+            slice::from_raw_parts_mut(water_covers, len).copy_from_slice(&solution.water_covers);
+        }
+        return RAIN_OK;
+    });
+}
+
+/// Returns a static, NUL terminated description of a status code.
+#[no_mangle]
+pub extern "C" fn rain_strerror(status: i32) -> *const c_char {
+    let unknown: &'static [u8] = b"unknown status\0";
+    let message = guarded(unknown, || {
+        return match status {
+            RAIN_OK => b"ok\0",
+            RAIN_NULL_POINTER => b"null pointer\0",
+            RAIN_EMPTY_PROFILE => b"profile is empty\0",
+            RAIN_OVERFLOW => b"amount of water exceeds u64\0",
+            RAIN_NOT_CONSERVED => b"water not conserved\0",
+            RAIN_UNSUPPORTED => b"unsupported options\0",
+            RAIN_PANIC => b"internal error\0",
+            RAIN_INVALID_INPUT => b"invalid input\0",
+            _ => unknown,
+        };
+    });
+    return message.as_ptr() as *const c_char;
+}
+
+// guarded runs the body of an entry point, a panic returns the fallback
+fn guarded<T, F: FnOnce() -> T + UnwindSafe>(fallback: T, body: F) -> T {
+    return panic::catch_unwind(body).unwrap_or(fallback);
+}
+
+fn status(err: &Error) -> i32 {
+    return match err {
+        Error::EmptyProfile => RAIN_EMPTY_PROFILE,
+        Error::Overflow => RAIN_OVERFLOW,
+        Error::Conservation { .. } => RAIN_NOT_CONSERVED,
+        Error::Unsupported(_) => RAIN_UNSUPPORTED,
+        Error::InvalidInput(_) => RAIN_INVALID_INPUT,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+    use std::ptr;
+
+    #[test]
+    fn solve_through_ffi() {
+        let heights = [3u64, 1, 6, 4, 8, 9];
+        let mut levels = [0.0; 6];
+        let mut covers = [0.0; 6];
+        let status = unsafe {
+            rain_solve(
+                heights.as_ptr(),
+                6,
+                1,
+                levels.as_mut_ptr(),
+                covers.as_mut_ptr(),
+            )
+        };
+        assert_eq!(status, RAIN_OK);
+        assert_eq!(levels, [4.0, 4.0, 6.0, 6.0, 8.0, 9.0]);
+        assert_eq!(covers, [1.0, 3.0, 0.0, 2.0, 0.0, 0.0]);
+
+        let status =
+            unsafe { rain_solve(heights.as_ptr(), 6, 1, levels.as_mut_ptr(), ptr::null_mut()) };
+        assert_eq!(status, RAIN_OK);
+    }
+
+    #[test]
+    fn error_codes() {
+        let mut levels = [0.0; 2];
+        let heights = [u64::MAX, 0];
+        unsafe {
+            assert_eq!(
+                rain_solve(ptr::null(), 2, 1, levels.as_mut_ptr(), ptr::null_mut()),
+                RAIN_NULL_POINTER
+            );
+            assert_eq!(
+                rain_solve(heights.as_ptr(), 0, 1, levels.as_mut_ptr(), ptr::null_mut()),
+                RAIN_EMPTY_PROFILE
+            );
+            assert_eq!(
+                rain_solve(heights.as_ptr(), 2, 1, levels.as_mut_ptr(), ptr::null_mut()),
+                RAIN_OVERFLOW
+            );
+        }
+        assert_eq!(levels, [0.0, 0.0]);
+        let message = unsafe { CStr::from_ptr(rain_strerror(RAIN_OVERFLOW)) };
+        assert_eq!(message.to_str().unwrap(), "amount of water exceeds u64");
+        assert_eq!(status(&Error::InvalidInput("")), RAIN_INVALID_INPUT);
+    }
+
+    #[test]
+    fn panics_are_caught() {
+        assert_eq!(guarded(RAIN_PANIC, || RAIN_OK), RAIN_OK);
+        assert_eq!(guarded(RAIN_PANIC, || panic!("in the solver")), RAIN_PANIC);
+        let message = unsafe { CStr::from_ptr(rain_strerror(RAIN_PANIC)) };
+        assert_eq!(message.to_str().unwrap(), "internal error");
+    }
+}
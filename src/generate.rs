@@ -0,0 +1,240 @@
+// module generate provides synthetic terrain profiles for tests, benchmarks
+// and demos. All random generators are seeded and reproducible; heights are
+// within low..=high.
+
+// Params collects the parameters of all generators, each uses what it needs
+#[derive(Debug, Clone)]
+pub struct Params {
+    pub length: usize,
+    pub low: u64,
+    pub high: u64,
+    pub seed: u64,
+    pub period: usize,  // sawtooth tooth, stair step and plateau width
+    pub roughness: f64, // midpoint displacement
+}
+
+impl Default for Params {
+    fn default() -> Params {
+        Params {
+            length: 100,
+            low: 0,
+            high: 9,
+            seed: 0,
+            period: 10,
+            roughness: 0.7,
+        }
+    }
+}
+
+// names of generators known to `profile`
+pub const KINDS: [&str; 8] = [
+    "uniform", "walk", "fractal", "sawtooth", "valley", "stairs", "ramp", "plateaus",
+];
+
+// profile runs the generator of a given name, None if there is no such one
+pub fn profile(kind: &str, p: &Params) -> Option<Vec<u64>> {
+    let profile = match kind {
+        "uniform" => uniform(p.length, p.low, p.high, p.seed),
+        "walk" => random_walk(p.length, p.low, p.high, p.seed),
+        "fractal" => midpoint(p.length, p.low, p.high, p.roughness, p.seed),
+        "sawtooth" => sawtooth(p.length, p.low, p.high, p.period),
+        "valley" => valley(p.length, p.low, p.high),
+        "stairs" => stairs(p.length, p.low, p.high, p.period),
+        "ramp" => ramp(p.length, p.low, p.high),
+        "plateaus" => plateaus(p.length, p.low, p.high, p.period, p.seed),
+        _ => return None,
+    };
+    return Some(profile);
+}
+
+// Rng is a small splitmix64 generator, good enough for terrain and without
+// dependencies. The same seed gives the same profile on every platform.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        return z ^ (z >> 31);
+    }
+
+    // uniform integer in low..=high
+    pub fn range(&mut self, low: u64, high: u64) -> u64 {
+        let span = high - low;
+        if span == u64::MAX {
+            return self.next_u64();
+        }
+        return low + self.next_u64() % (span + 1);
+    }
+
+    // uniform float in [-1, 1)
+    pub fn signed_unit(&mut self) -> f64 {
+        return (self.next_u64() >> 11) as f64 / (1u64 << 52) as f64 - 1.0;
+    }
+}
+
+// ramp rises linearly from low at the first to high at the last segment
+pub fn ramp(length: usize, low: u64, high: u64) -> Vec<u64> {
+    if length < 2 {
+        return vec![low; length];
+    }
+    let span = (high - low) as f64;
+    return (0..length)
+        .map(|i| low + (span * i as f64 / (length - 1) as f64).round() as u64)
+        .collect();
+}
+
+// sawtooth repeats teeth of `period` segments that rise from low to high,
+// every tooth is a peak dividing the profile
+pub fn sawtooth(length: usize, low: u64, high: u64, period: usize) -> Vec<u64> {
+    let tooth = ramp(period.max(1), low, high);
+    return tooth.iter().copied().cycle().take(length).collect();
+}
+
+// valley falls linearly from high at both ends to low in the middle
+pub fn valley(length: usize, low: u64, high: u64) -> Vec<u64> {
+    if length < 2 {
+        return vec![low; length];
+    }
+    let span = (high - low) as f64;
+    let middle = (length - 1) as f64 / 2.0;
+    return (0..length)
+        .map(|i| low + (span * (i as f64 - middle).abs() / middle).round() as u64)
+        .collect();
+}
+
+// stairs rise from low to high in flat steps of `width` segments
+pub fn stairs(length: usize, low: u64, high: u64, width: usize) -> Vec<u64> {
+    let width = width.max(1);
+    let steps = ramp(length.div_ceil(width), low, high);
+    return (0..length).map(|i| steps[i / width]).collect();
+}
+
+// uniform draws every segment independently
+pub fn uniform(length: usize, low: u64, high: u64, seed: u64) -> Vec<u64> {
+    let mut rng = Rng::new(seed);
+    return (0..length).map(|_| rng.range(low, high)).collect();
+}
+
+// random_walk steps up, down or not at all from one segment to the next,
+// it reflects at low and high
+pub fn random_walk(length: usize, low: u64, high: u64, seed: u64) -> Vec<u64> {
+    let mut rng = Rng::new(seed);
+    let mut height = rng.range(low, high);
+    let mut profile = Vec::with_capacity(length);
+    for _ in 0..length {
+        profile.push(height);
+        height = match rng.next_u64() % 3 {
+            0 if height > low => height - 1,
+            0 => height + (high > low) as u64,
+            1 if height < high => height + 1,
+            1 => height - (high > low) as u64,
+            _ => height,
+        };
+    }
+    return profile;
+}
+
+// midpoint displacement gives a fractal (Brownian-like) profile. Each
+// halving of the interval scales the random displacement by 2^-roughness,
+// roughness 0.5 to 1.0 looks like natural terrain.
+pub fn midpoint(length: usize, low: u64, high: u64, roughness: f64, seed: u64) -> Vec<u64> {
+    let mut rng = Rng::new(seed);
+    let n = length.max(2).next_power_of_two();
+    let mut heights = vec![0.0; n + 1];
+    heights[0] = rng.signed_unit();
+    heights[n] = rng.signed_unit();
+    let mut step = n;
+    let mut scale = 1.0;
+    while step > 1 {
+        let half = step / 2;
+        for start in (0..n).step_by(step) {
+            let mean = (heights[start] + heights[start + step]) / 2.0;
+            heights[start + half] = mean + scale * rng.signed_unit();
+        }
+        step = half;
+        scale *= 0.5f64.powf(roughness);
+    }
+    heights.truncate(length);
+    return scale_to(&heights, low, high);
+}
+
+// plateaus are flat runs of random height and random width up to `width`
+pub fn plateaus(length: usize, low: u64, high: u64, width: usize, seed: u64) -> Vec<u64> {
+    let mut rng = Rng::new(seed);
+    let mut profile = Vec::with_capacity(length);
+    while profile.len() < length {
+        let run = rng.range(1, width.max(1) as u64) as usize;
+        let height = rng.range(low, high);
+        let run = run.min(length - profile.len());
+        profile.extend(std::iter::repeat_n(height, run));
+    }
+    return profile;
+}
+
+// scale_to maps float heights linearly onto low..=high
+fn scale_to(heights: &[f64], low: u64, high: u64) -> Vec<u64> {
+    let min = heights.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = heights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let span = (high - low) as f64;
+    return heights
+        .iter()
+        .map(|&h| {
+            if max > min {
+                low + (span * (h - min) / (max - min)).round() as u64
+            } else {
+                low
+            }
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lengths_and_ranges() {
+        let profiles = [
+            ramp(37, 2, 9),
+            sawtooth(37, 2, 9, 4),
+            uniform(37, 2, 9, 1),
+            random_walk(37, 2, 9, 1),
+            midpoint(37, 2, 9, 0.7, 1),
+            plateaus(37, 2, 9, 5, 1),
+            valley(37, 2, 9),
+            stairs(37, 2, 9, 5),
+        ];
+        for profile in profiles.iter() {
+            assert_eq!(profile.len(), 37);
+            assert!(profile.iter().all(|&h| (2..=9).contains(&h)));
+        }
+        assert_eq!(ramp(3, 0, 4), vec![0, 2, 4]);
+        assert_eq!(sawtooth(7, 0, 2, 3), vec![0, 1, 2, 0, 1, 2, 0]);
+        assert_eq!(midpoint(1, 0, 9, 0.7, 1), vec![0]);
+        assert!(plateaus(0, 0, 9, 5, 1).is_empty());
+        assert_eq!(valley(5, 0, 4), vec![4, 2, 0, 2, 4]);
+        assert_eq!(stairs(7, 0, 4, 3), vec![0, 0, 0, 2, 2, 2, 4]);
+        for kind in KINDS.iter() {
+            assert!(profile(kind, &Params::default()).is_some());
+        }
+        assert!(profile("spiral", &Params::default()).is_none());
+    }
+
+    #[test]
+    fn reproducible() {
+        assert_eq!(uniform(100, 0, 50, 7), uniform(100, 0, 50, 7));
+        assert_ne!(uniform(100, 0, 50, 7), uniform(100, 0, 50, 8));
+        assert_eq!(midpoint(100, 0, 50, 0.5, 7), midpoint(100, 0, 50, 0.5, 7));
+        let walk = random_walk(1000, 0, 3, 7);
+        assert!(walk.windows(2).all(|w| w[0].abs_diff(w[1]) <= 1));
+    }
+}
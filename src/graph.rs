@@ -0,0 +1,1075 @@
+// module graph floods terrain of any connectivity: cells with a height and
+// an area, joined by undirected edges. Grids, hexagonal grids, irregular
+// meshes and networks of connected tanks are graphs, a profile is a path.
+// Every cell receives one unit of rain per day and unit of area.
+//
+// The cells are added in order of height and joined to the regions of their
+// lower neighbours (a priority flood of the sublevel sets). A cell that
+// joins two or more regions holding water is a saddle, the regions become
+// the children of a node of the merge tree at its height. All saddles of
+// one height around a region join into the same node, like equal peaks in
+// tree::MergeTree. Flats at the height of a saddle hold no water and are
+// absorbed.
+//
+// The simulation follows events::Engine. Rain on a flat (a connected set of
+// cells of equal height, often a single cell) runs down the edges leaving
+// it, in equal parts per edge, and on into the basin floors. A basin fills
+// until it reaches the level of its saddles. Full neighbouring basins form a
+// pool, whose inflow goes in equal parts to the basins next to the pool,
+// over the saddles between them. Once all children of a node are full, they
+// join into its lake. With open boundaries the outlets drain into the
+// outside, a basin below all cells that never fills.
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+use crate::solutions::Solution;
+use crate::{Boundary, Error};
+
+// Graph stores the edges as adjacency lists in one vector, the neighbours of
+// cell i are targets[offsets[i]..offsets[i + 1]]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Graph {
+    heights: Vec<u64>,
+    areas: Vec<f64>,
+    outlets: Vec<bool>,
+    offsets: Vec<usize>,
+    targets: Vec<usize>,
+}
+
+impl Graph {
+    // new checks the cells and builds the adjacency. Loops and repeated
+    // edges are dropped. With open boundaries water leaves at the outlets.
+    pub fn new(
+        heights: Vec<u64>,
+        areas: Vec<f64>,
+        edges: &[(usize, usize)],
+        outlets: &[usize],
+    ) -> Result<Graph, Error> {
+        let size = heights.len();
+        if size == 0 {
+            return Err(Error::EmptyProfile);
+        }
+        if areas.len() != size || areas.iter().any(|&a| !a.is_finite() || a <= 0.0) {
+            return Err(Error::InvalidInput("areas must be positive, one per cell"));
+        }
+        let outside = |&c: &usize| c >= size;
+        if edges.iter().any(|(a, b)| outside(a) || outside(b)) || outlets.iter().any(outside) {
+            return Err(Error::InvalidInput("edges and outlets must refer to cells"));
+        }
+
+        let mut lists = vec![Vec::new(); size];
+        for &(a, b) in edges.iter().filter(|(a, b)| a != b) {
+            lists[a].push(b);
+            lists[b].push(a);
+        }
+        let mut offsets = vec![0];
+        let mut targets = Vec::with_capacity(2 * edges.len());
+        for list in lists.iter_mut() {
+            list.sort_unstable();
+            list.dedup();
+            targets.extend(list.iter());
+            offsets.push(targets.len());
+        }
+        let mut is_outlet = vec![false; size];
+        for &cell in outlets.iter() {
+            is_outlet[cell] = true;
+        }
+
+        let graph = Graph {
+            heights,
+            areas,
+            outlets: is_outlet,
+            offsets,
+            targets,
+        };
+        if !graph.connected() {
+            return Err(Error::InvalidInput("cells must be connected"));
+        }
+        return Ok(graph);
+    }
+
+    // path is the graph of a profile: unit segments in a row, open at both
+    // ends
+    pub fn path(profile: &[u64]) -> Result<Graph, Error> {
+        let size = profile.len();
+        let edges: Vec<(usize, usize)> = (1..size).map(|i| (i - 1, i)).collect();
+        let outlets = if size > 0 { vec![0, size - 1] } else { vec![] };
+        return Graph::new(profile.to_vec(), vec![1.0; size], &edges, &outlets);
+    }
+
+    pub fn heights(&self) -> &[u64] {
+        return &self.heights;
+    }
+
+    pub fn areas(&self) -> &[f64] {
+        return &self.areas;
+    }
+
+    pub fn neighbours(&self, cell: usize) -> &[usize] {
+        return &self.targets[self.offsets[cell]..self.offsets[cell + 1]];
+    }
+
+    pub fn is_outlet(&self, cell: usize) -> bool {
+        return self.outlets[cell];
+    }
+
+    // volume of water in a solution, water covers weighted by area
+    pub fn volume(&self, solution: &Solution) -> f64 {
+        return solution
+            .water_covers
+            .iter()
+            .zip(self.areas.iter())
+            .map(|(w, a)| w * a)
+            .sum();
+    }
+
+    fn connected(&self) -> bool {
+        let mut seen = vec![false; self.heights.len()];
+        let mut stack = vec![0];
+        seen[0] = true;
+        let mut count = 1;
+        while let Some(cell) = stack.pop() {
+            for &n in self.neighbours(cell) {
+                if !seen[n] {
+                    seen[n] = true;
+                    count += 1;
+                    stack.push(n);
+                }
+            }
+        }
+        return count == self.heights.len();
+    }
+}
+
+// solve floods a graph with `duration` days of rain, levels and water covers
+// of the solution are per cell. Open boundaries without outlets are closed,
+// then the water held must equal the rain like in crate::solve.
+pub fn solve(graph: &Graph, duration: u64, boundary: Boundary) -> Result<Solution, Error> {
+    let levels = match trivial(graph, duration, boundary) {
+        Some(levels) => levels,
+        None => flood(graph, duration as f64, boundary),
+    };
+    let solution = Solution::new(levels, &graph.heights);
+
+    if boundary == Boundary::Closed || !graph.outlets.contains(&true) {
+        let expected = duration as f64 * graph.areas.iter().sum::<f64>();
+        let received = graph.volume(&solution);
+        let tolerance = crate::Options::default().tolerance;
+        if (received - expected).abs() > tolerance * expected.max(1.0) {
+            return Err(Error::Conservation { expected, received });
+        }
+    }
+    return Ok(solution);
+}
+
+// the trivial cases of solutions::select_fn with areas: no rain, a flat
+// world, and enough rain to cover all land. With open boundaries a flat
+// world stays dry and there is no saturation.
+fn trivial(graph: &Graph, duration: u64, boundary: Boundary) -> Option<Vec<f64>> {
+    let grounds = &graph.heights;
+    let max = *grounds.iter().max().unwrap();
+    let min = *grounds.iter().min().unwrap();
+    let dry: Vec<f64> = grounds.iter().map(|&g| g as f64).collect();
+
+    if duration == 0 {
+        return Some(dry);
+    }
+    if boundary == Boundary::Open && graph.outlets.contains(&true) {
+        return if max == min { Some(dry) } else { None };
+    }
+    if max == min {
+        return Some(vec![max as f64 + duration as f64; grounds.len()]);
+    }
+
+    // saturation: the water level equals the highest land, or is above it
+    let area: f64 = graph.areas.iter().sum();
+    let saturation_water: f64 = grounds
+        .iter()
+        .zip(graph.areas.iter())
+        .map(|(&g, a)| (max - g) as f64 * a)
+        .sum();
+    let water_tot = duration as f64 * area;
+    if water_tot >= saturation_water {
+        let level = max as f64 + (water_tot - saturation_water) / area;
+        return Some(vec![level; grounds.len()]);
+    }
+    return None;
+}
+
+// Basin is a node of the merge tree. level: height of the saddles at which
+// its children join, the floor for a leaf. capacity: water it holds up to
+// the saddles of its parent. saddles: the saddles it touches, which lead to
+// its siblings. ocean: it contains the outside and never fills.
+struct Basin {
+    level: u64,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    capacity: f64,
+    saddles: Vec<usize>,
+    ocean: bool,
+}
+
+// Link leads over a saddle into a sibling, water crossing it runs into the
+// leaf entry
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Link {
+    sibling: usize,
+    entry: usize,
+    saddle: usize,
+}
+
+// Tree is the merge tree. saddles: the children every saddle touches, each
+// with the leaf into which water crossing the saddle runs. drain: the leaf
+// downhill of every cell. owner: the node every cell belongs to, cells are
+// part of a lake once it rises above them. rain: rain per leaf and day.
+struct Tree {
+    basins: Vec<Basin>,
+    saddles: Vec<Vec<(usize, usize)>>,
+    root: usize,
+    drain: Vec<usize>,
+    owner: Vec<usize>,
+    rain: Vec<f64>,
+}
+
+// Region is a connected set of cells during the construction, kept at the
+// representative of a disjoint set. land: sum of heights times areas. flat
+// holds the cells of a region that does not hold water yet.
+#[derive(Clone, Default)]
+struct Region {
+    node: usize,
+    min: u64,
+    ocean: bool,
+    area: f64,
+    land: f64,
+    flat: Vec<usize>,
+}
+
+fn find(sets: &mut [usize], x: usize) -> usize {
+    let mut root = x;
+    while sets[root] != root {
+        root = sets[root];
+    }
+    let mut x = x;
+    while sets[x] != root {
+        let next = sets[x];
+        sets[x] = root;
+        x = next;
+    }
+    return root;
+}
+
+fn new_basin(basins: &mut Vec<Basin>, level: u64, ocean: bool) -> usize {
+    basins.push(Basin {
+        level,
+        parent: None,
+        children: Vec::new(),
+        capacity: f64::INFINITY,
+        saddles: Vec::new(),
+        ocean,
+    });
+    return basins.len() - 1;
+}
+
+impl Tree {
+    fn new(graph: &Graph, boundary: Boundary) -> Tree {
+        let heights = &graph.heights;
+        let size = heights.len();
+        let outside = size;
+        let mut order: Vec<usize> = (0..size).collect();
+        order.sort_by_key(|&c| (heights[c], c));
+
+        let mut basins: Vec<Basin> = Vec::new();
+        let mut sets: Vec<usize> = (0..=size).collect();
+        let mut regions = vec![Region::default(); size + 1];
+        let mut active = vec![false; size];
+        let mut drain = vec![usize::MAX; size];
+        let mut owner = vec![usize::MAX; size];
+        // merge nodes of one height that joined into another one
+        let mut alias: Vec<Option<usize>> = Vec::new();
+        let open = boundary == Boundary::Open;
+        let ocean_leaf = new_basin(&mut basins, 0, true);
+        regions[outside] = Region {
+            node: ocean_leaf,
+            ocean: true,
+            ..Region::default()
+        };
+
+        for &cell in order.iter() {
+            let h = heights[cell];
+            let area = graph.areas[cell];
+
+            // neighbouring regions, and the lowest neighbour into which the
+            // cell drains. Flats at this height do not count, they drain
+            // through it.
+            let mut touching: Vec<usize> = Vec::new();
+            let mut lowest: Option<usize> = None;
+            for &n in graph.neighbours(cell).iter().filter(|&&n| active[n]) {
+                let root = find(&mut sets, n);
+                if !touching.contains(&root) {
+                    touching.push(root);
+                }
+                let wet = regions[root].ocean || regions[root].min < h;
+                if wet && lowest.is_none_or(|l| (heights[n], n) < (heights[l], l)) {
+                    lowest = Some(n);
+                }
+            }
+            let mut down = lowest.map(|l| drain[l]);
+            if open && graph.outlets[cell] {
+                let root = find(&mut sets, outside);
+                if !touching.contains(&root) {
+                    touching.push(root);
+                }
+                down = Some(ocean_leaf);
+            }
+            active[cell] = true;
+
+            // a new floor
+            if touching.is_empty() {
+                let leaf = new_basin(&mut basins, h, false);
+                drain[cell] = leaf;
+                owner[cell] = leaf;
+                regions[cell] = Region {
+                    node: leaf,
+                    min: h,
+                    ocean: false,
+                    area,
+                    land: h as f64 * area,
+                    flat: vec![cell],
+                };
+                continue;
+            }
+
+            let (wet, flats): (Vec<usize>, Vec<usize>) = touching
+                .into_iter()
+                .partition(|&r| regions[r].ocean || regions[r].min < h);
+
+            // flats at the height of this cell join into one flat
+            if wet.is_empty() {
+                let mut keep = flats[0];
+                for &r in flats.iter() {
+                    if regions[r].flat.len() > regions[keep].flat.len() {
+                        keep = r;
+                    }
+                }
+                let leaf = regions[keep].node;
+                for &r in flats.iter().filter(|&&r| r != keep) {
+                    let region = std::mem::take(&mut regions[r]);
+                    for &c in region.flat.iter() {
+                        drain[c] = leaf;
+                        owner[c] = leaf;
+                    }
+                    regions[keep].flat.extend(region.flat);
+                    regions[keep].area += region.area;
+                    regions[keep].land += region.land;
+                    sets[r] = keep;
+                }
+                drain[cell] = leaf;
+                owner[cell] = leaf;
+                regions[keep].flat.push(cell);
+                regions[keep].area += area;
+                regions[keep].land += h as f64 * area;
+                sets[cell] = keep;
+                continue;
+            }
+
+            // the cell is a saddle if it touches two or more wet regions.
+            // They become children of one node at this height, a region that
+            // is such a node already brings its children.
+            drain[cell] = down.unwrap();
+            let current = wet[0];
+            if wet.len() > 1 {
+                let is_saddle = |basins: &[Basin], node: usize| {
+                    return basins[node].level == h && !basins[node].children.is_empty();
+                };
+                let merged = match wet
+                    .iter()
+                    .map(|&r| regions[r].node)
+                    .find(|&n| is_saddle(&basins, n))
+                {
+                    Some(node) => node,
+                    None => new_basin(&mut basins, h, false),
+                };
+                alias.resize(basins.len(), None);
+                for &r in wet.iter() {
+                    let node = regions[r].node;
+                    if node == merged {
+                        continue;
+                    }
+                    if is_saddle(&basins, node) {
+                        let children = std::mem::take(&mut basins[node].children);
+                        for &child in children.iter() {
+                            basins[child].parent = Some(merged);
+                        }
+                        basins[merged].children.extend(children);
+                        alias[node] = Some(merged);
+                    } else {
+                        basins[node].parent = Some(merged);
+                        basins[node].capacity = if regions[r].ocean {
+                            f64::INFINITY
+                        } else {
+                            regions[r].area * h as f64 - regions[r].land
+                        };
+                        basins[merged].children.push(node);
+                    }
+                }
+                for &r in wet.iter().skip(1) {
+                    let region = std::mem::take(&mut regions[r]);
+                    sets[r] = current;
+                    let kept = &mut regions[current];
+                    kept.min = kept.min.min(region.min);
+                    kept.ocean |= region.ocean;
+                    kept.area += region.area;
+                    kept.land += region.land;
+                }
+                regions[current].node = merged;
+                basins[merged].ocean = regions[current].ocean;
+            }
+
+            // flats around the cell drain through it
+            let node = regions[current].node;
+            for &r in flats.iter() {
+                let region = std::mem::take(&mut regions[r]);
+                for &c in region.flat.iter() {
+                    drain[c] = drain[cell];
+                    owner[c] = node;
+                }
+                regions[current].area += region.area;
+                regions[current].land += region.land;
+                sets[r] = current;
+            }
+            owner[cell] = node;
+            regions[current].area += area;
+            regions[current].land += h as f64 * area;
+            regions[current].flat.clear();
+            sets[cell] = current;
+        }
+
+        alias.resize(basins.len(), None);
+        for node in owner.iter_mut() {
+            while let Some(merged) = alias[*node] {
+                *node = merged;
+            }
+        }
+        let root = regions[find(&mut sets, 0)].node;
+        let mut tree = Tree {
+            basins,
+            saddles: Vec::new(),
+            root,
+            drain,
+            owner,
+            rain: Vec::new(),
+        };
+        tree.link_siblings(graph, ocean_leaf);
+        tree.route_rain(graph, &order, open, ocean_leaf);
+        return tree;
+    }
+
+    // ranges of the depth first order of the tree, a node's subtree is
+    // enter[node]..leave[node]
+    fn depth_first(&self) -> (Vec<usize>, Vec<usize>) {
+        let size = self.basins.len();
+        let (mut enter, mut leave) = (vec![0; size], vec![0; size]);
+        let mut counter = 0;
+        let mut stack = vec![(self.root, false)];
+        while let Some((node, done)) = stack.pop() {
+            if done {
+                leave[node] = counter;
+                continue;
+            }
+            enter[node] = counter;
+            counter += 1;
+            stack.push((node, true));
+            stack.extend(self.basins[node].children.iter().map(|&c| (c, false)));
+        }
+        return (enter, leave);
+    }
+
+    // link_siblings finds the saddles between the children of every node:
+    // connected cells at the height of the node, touching two or more
+    // children.
+    fn link_siblings(&mut self, graph: &Graph, ocean_leaf: usize) {
+        let heights = &graph.heights;
+        let size = heights.len();
+        let (enter, _) = self.depth_first();
+        let mut sorted: Vec<Vec<usize>> = self.basins.iter().map(|b| b.children.clone()).collect();
+        for children in sorted.iter_mut() {
+            children.sort_by_key(|&c| enter[c]);
+        }
+        // the child of a node whose subtree holds a descendant
+        let child_of = |node: usize, descendant: usize| -> usize {
+            let children = &sorted[node];
+            let k = children.partition_point(|&c| enter[c] <= enter[descendant]);
+            return children[k - 1];
+        };
+
+        // the node whose saddles a cell is part of
+        let saddle: Vec<Option<usize>> = (0..size)
+            .map(|cell| {
+                let owner = self.owner[cell];
+                let at = |node: usize| {
+                    return self.basins[node].level == heights[cell]
+                        && !self.basins[node].children.is_empty();
+                };
+                if at(owner) {
+                    return Some(owner);
+                }
+                return self.basins[owner].parent.filter(|&p| at(p));
+            })
+            .collect();
+
+        let mut sets: Vec<usize> = (0..size).collect();
+        for cell in 0..size {
+            if saddle[cell].is_none() {
+                continue;
+            }
+            for &n in graph.neighbours(cell) {
+                if heights[n] == heights[cell] && saddle[n] == saddle[cell] {
+                    let (a, b) = (find(&mut sets, cell), find(&mut sets, n));
+                    sets[a] = b;
+                }
+            }
+        }
+
+        // children touched by each saddle, with the leaf to enter them
+        let mut touched: Vec<Vec<(usize, usize)>> = vec![Vec::new(); size];
+        let mut seen = HashSet::new();
+        for cell in 0..size {
+            let node = match saddle[cell] {
+                Some(node) => node,
+                None => continue,
+            };
+            let mut entries = Vec::new();
+            if self.owner[cell] != node {
+                entries.push((child_of(node, self.owner[cell]), self.drain[cell]));
+            }
+            for &n in graph.neighbours(cell) {
+                if heights[n] < heights[cell] {
+                    entries.push((child_of(node, self.owner[n]), self.drain[n]));
+                }
+            }
+            if graph.outlets[cell] && self.basins[node].ocean {
+                entries.push((child_of(node, ocean_leaf), ocean_leaf));
+            }
+            let root = find(&mut sets, cell);
+            for entry in entries {
+                if seen.insert((root, entry.0)) {
+                    touched[root].push(entry);
+                }
+            }
+        }
+        for children in touched.into_iter().filter(|t| t.len() > 1) {
+            for &(child, _) in children.iter() {
+                self.basins[child].saddles.push(self.saddles.len());
+            }
+            self.saddles.push(children);
+        }
+    }
+
+    // route_rain runs the rain downhill from flat to flat, in equal parts
+    // over the edges leaving a flat, into the leaves
+    fn route_rain(&mut self, graph: &Graph, order: &[usize], open: bool, ocean_leaf: usize) {
+        let heights = &graph.heights;
+        let size = heights.len();
+        let mut flat_of = vec![usize::MAX; size];
+        let mut inflow = vec![0.0; size];
+        self.rain = vec![0.0; self.basins.len()];
+
+        for &start in order.iter().rev() {
+            if flat_of[start] != usize::MAX {
+                continue;
+            }
+            // collect the flat of the cell, and the edges leaving it
+            let h = heights[start];
+            let mut flat = vec![start];
+            let mut exits: Vec<Option<usize>> = Vec::new();
+            flat_of[start] = start;
+            let mut k = 0;
+            while k < flat.len() {
+                let cell = flat[k];
+                k += 1;
+                for &n in graph.neighbours(cell) {
+                    if heights[n] < h {
+                        exits.push(Some(n));
+                    } else if heights[n] == h && flat_of[n] == usize::MAX {
+                        flat_of[n] = start;
+                        flat.push(n);
+                    }
+                }
+                if open && graph.outlets[cell] {
+                    exits.push(None);
+                }
+            }
+
+            let water: f64 = flat.iter().map(|&c| inflow[c] + graph.areas[c]).sum();
+            if exits.is_empty() {
+                self.rain[self.drain[start]] += water;
+                continue;
+            }
+            let share = water / exits.len() as f64;
+            for exit in exits {
+                match exit {
+                    Some(n) => inflow[n] += share,
+                    None => self.rain[ocean_leaf] += share,
+                }
+            }
+        }
+    }
+}
+
+// state of a basin in the simulation
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Dry,
+    Filling,
+    Full,
+    Merged,
+}
+
+// Pending is a basin expected to be full at a time
+#[derive(Debug)]
+struct Pending {
+    time: f64,
+    node: usize,
+    version: u64,
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        return self.cmp(other) == Ordering::Equal;
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+// earliest time first, then lowest node
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        return other
+            .time
+            .total_cmp(&self.time)
+            .then_with(|| other.node.cmp(&self.node));
+    }
+}
+
+// Flood runs fill, spill and merge on the tree, jumping from one full basin
+// to the next. up points from merged basins to the lake they joined. Full
+// siblings form pools, kept as disjoint sets at their representative with
+// the surplus, the saddles around the pool and the links over them that
+// receive the surplus. pooled is the water a basin receives from the pools
+// of its siblings. seen marks pools and saddles already counted in a fill.
+struct Flood<'a> {
+    tree: &'a Tree,
+    state: Vec<State>,
+    volume: Vec<f64>,
+    stamp: Vec<f64>,
+    rate: Vec<f64>,
+    pooled: Vec<f64>,
+    up: Vec<usize>,
+    pool: Vec<usize>,
+    surplus: Vec<f64>,
+    shore: Vec<Vec<usize>>,
+    reach: Vec<Vec<Link>>,
+    seen: (Vec<u64>, Vec<u64>),
+    epoch: u64,
+    queue: BinaryHeap<Pending>,
+    version: Vec<u64>,
+    time: f64,
+}
+
+impl<'a> Flood<'a> {
+    fn new(tree: &'a Tree) -> Flood<'a> {
+        let size = tree.basins.len();
+        let mut flood = Flood {
+            tree,
+            state: vec![State::Dry; size],
+            volume: vec![0.0; size],
+            stamp: vec![0.0; size],
+            rate: tree.rain.clone(),
+            pooled: vec![0.0; size],
+            up: (0..size).collect(),
+            pool: (0..size).collect(),
+            surplus: vec![0.0; size],
+            shore: vec![Vec::new(); size],
+            reach: vec![Vec::new(); size],
+            seen: (vec![0; size], vec![0; tree.saddles.len()]),
+            epoch: 0,
+            queue: BinaryHeap::new(),
+            version: vec![0; size],
+            time: 0.0,
+        };
+        for (id, basin) in tree.basins.iter().enumerate() {
+            if basin.children.is_empty() && (basin.parent.is_some() || id == tree.root) {
+                flood.state[id] = State::Filling;
+                flood.schedule(id);
+            }
+        }
+        return flood;
+    }
+
+    fn volume_now(&self, node: usize) -> f64 {
+        return match self.state[node] {
+            State::Filling => self.volume[node] + self.rate[node] * (self.time - self.stamp[node]),
+            _ => self.volume[node],
+        };
+    }
+
+    fn settle(&mut self, node: usize) {
+        self.volume[node] = self.volume_now(node);
+        self.stamp[node] = self.time;
+    }
+
+    fn schedule(&mut self, node: usize) {
+        self.version[node] += 1;
+        let room = self.tree.basins[node].capacity - self.volume[node];
+        if !room.is_finite() || self.rate[node] <= 0.0 {
+            return;
+        }
+        self.queue.push(Pending {
+            time: self.time + (room / self.rate[node]).max(0.0),
+            node,
+            version: self.version[node],
+        });
+        // pools reschedule many lakes at once, drop outdated events
+        if self.queue.len() > 2 * self.version.len() + 1024 {
+            let version = &self.version;
+            self.queue.retain(|p| p.version == version[p.node]);
+        }
+    }
+
+    fn advance(&mut self, until: f64) {
+        while let Some(next) = self.queue.peek() {
+            if next.time > until {
+                break;
+            }
+            let Pending {
+                time,
+                node,
+                version,
+            } = self.queue.pop().unwrap();
+            if version != self.version[node] || self.state[node] != State::Filling {
+                continue;
+            }
+            self.time = time;
+            self.fill(node);
+        }
+        self.time = until;
+    }
+
+    // a basin reached its saddles: it joins the pools of full siblings next
+    // to it, the pool passes its surplus on or all siblings join
+    fn fill(&mut self, node: usize) {
+        self.settle(node);
+        let tree = self.tree;
+        self.volume[node] = tree.basins[node].capacity;
+        self.state[node] = State::Full;
+
+        // what the neighbouring pools gave to this basin is theirs
+        let own = self.rate[node] - self.pooled[node];
+        self.rate[node] = own;
+        self.pooled[node] = 0.0;
+
+        self.epoch += 1;
+        let epoch = self.epoch;
+        let mut pools: Vec<usize> = Vec::new();
+        for &saddle in tree.basins[node].saddles.iter() {
+            for &(sibling, _) in tree.saddles[saddle].iter() {
+                if self.state[sibling] == State::Full {
+                    let pool = find(&mut self.pool, sibling);
+                    if self.seen.0[pool] != epoch {
+                        self.seen.0[pool] = epoch;
+                        pools.push(pool);
+                    }
+                }
+            }
+        }
+
+        // join the pools, take back what they gave to their other neighbours
+        let mut surplus = own;
+        let mut shore = tree.basins[node].saddles.clone();
+        for pool in pools {
+            let old = std::mem::take(&mut self.reach[pool]);
+            let share = self.surplus[pool] / old.len() as f64;
+            for link in old.iter().filter(|l| l.sibling != node) {
+                self.send(link, -share);
+            }
+            shore.append(&mut self.shore[pool]);
+            surplus += self.surplus[pool];
+            self.pool[pool] = node;
+        }
+
+        // the pool reaches every sibling that is not full once over each
+        // saddle around it, saddles between full siblings only are inside
+        let state = &self.state;
+        let seen = &mut self.seen.1;
+        shore.retain(|&saddle| {
+            let new = seen[saddle] != epoch;
+            seen[saddle] = epoch;
+            let open = tree.saddles[saddle]
+                .iter()
+                .any(|&(c, _)| state[c] != State::Full);
+            return new && open;
+        });
+        let mut reach: Vec<Link> = Vec::new();
+        for &saddle in shore.iter() {
+            for &(sibling, entry) in tree.saddles[saddle].iter() {
+                if self.state[sibling] != State::Full {
+                    reach.push(Link {
+                        sibling,
+                        entry,
+                        saddle,
+                    });
+                }
+            }
+        }
+        self.shore[node] = shore;
+        self.surplus[node] = surplus;
+
+        if reach.is_empty() {
+            self.activate(tree.basins[node].parent.unwrap(), surplus);
+            return;
+        }
+        let share = surplus / reach.len() as f64;
+        for link in reach.iter() {
+            self.send(link, share);
+        }
+        self.reach[node] = reach;
+    }
+
+    // all children of a node are full, they join into its lake
+    fn activate(&mut self, node: usize, inflow: f64) {
+        let tree = self.tree;
+        let mut volume = 0.0;
+        for &child in tree.basins[node].children.iter() {
+            self.state[child] = State::Merged;
+            self.up[child] = node;
+            volume += tree.basins[child].capacity;
+        }
+        self.state[node] = State::Filling;
+        self.volume[node] = volume;
+        self.stamp[node] = self.time;
+        self.rate[node] = inflow;
+        self.schedule(node);
+    }
+
+    fn send(&mut self, link: &Link, amount: f64) {
+        self.pooled[link.sibling] += amount;
+        self.flow(link.entry, amount);
+    }
+
+    // water entering at a leaf runs into its lake, the pools of full lakes
+    // pass it on
+    fn flow(&mut self, leaf: usize, amount: f64) {
+        let mut stack = vec![(leaf, amount)];
+        while let Some((leaf, amount)) = stack.pop() {
+            let lake = find(&mut self.up, leaf);
+            match self.state[lake] {
+                State::Filling => {
+                    self.settle(lake);
+                    self.rate[lake] += amount;
+                    self.schedule(lake);
+                }
+                State::Full => {
+                    self.rate[lake] += amount;
+                    let pool = find(&mut self.pool, lake);
+                    self.surplus[pool] += amount;
+                    let share = amount / self.reach[pool].len() as f64;
+                    for k in 0..self.reach[pool].len() {
+                        let link = self.reach[pool][k];
+                        self.pooled[link.sibling] += share;
+                        stack.push((link.entry, share));
+                    }
+                }
+                _ => unreachable!("water runs into lakes only"),
+            }
+        }
+    }
+}
+
+// flood simulates the general case and reads the levels of all lakes
+fn flood(graph: &Graph, duration: f64, boundary: Boundary) -> Vec<f64> {
+    let tree = Tree::new(graph, boundary);
+    let mut flood = Flood::new(&tree);
+    flood.advance(duration);
+
+    // cells of every subtree as a contiguous range, in depth first order
+    let size = tree.basins.len();
+    let mut own: Vec<Vec<usize>> = vec![Vec::new(); size];
+    for (cell, &node) in tree.owner.iter().enumerate() {
+        own[node].push(cell);
+    }
+    let mut cells = Vec::with_capacity(graph.heights.len());
+    let mut range = vec![(0, 0); size];
+    let mut stack = vec![(tree.root, false)];
+    while let Some((node, done)) = stack.pop() {
+        if done {
+            cells.extend(own[node].iter());
+            range[node].1 = cells.len();
+            continue;
+        }
+        range[node].0 = cells.len();
+        stack.push((node, true));
+        stack.extend(tree.basins[node].children.iter().map(|&c| (c, false)));
+    }
+
+    let mut levels: Vec<f64> = graph.heights.iter().map(|&h| h as f64).collect();
+    for node in 0..size {
+        let basin = &tree.basins[node];
+        if basin.ocean {
+            continue;
+        }
+        let lake = &cells[range[node].0..range[node].1];
+        let level = match flood.state[node] {
+            State::Full => tree.basins[basin.parent.unwrap()].level as f64,
+            State::Filling => lake_level(lake, graph, flood.volume_now(node)),
+            _ => continue,
+        };
+        for &cell in lake.iter() {
+            if (graph.heights[cell] as f64) < level {
+                levels[cell] = level;
+            }
+        }
+    }
+    return levels;
+}
+
+// lake_level is the level at which the cells of a lake hold a volume
+fn lake_level(lake: &[usize], graph: &Graph, volume: f64) -> f64 {
+    let mut floor: Vec<(f64, f64)> = lake
+        .iter()
+        .map(|&c| (graph.heights[c] as f64, graph.areas[c]))
+        .collect();
+    floor.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (mut area, mut land) = (0.0, 0.0);
+    for k in 0..floor.len() {
+        area += floor[k].1;
+        land += floor[k].0 * floor[k].1;
+        let level = (volume + land) / area;
+        if k + 1 == floor.len() || level <= floor[k + 1].0 {
+            return level;
+        }
+    }
+    return floor[0].0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::Rng;
+    use crate::solutions;
+    use crate::zero::{f64similar, vecf64similar};
+    use crate::Problem;
+
+    #[test]
+    fn path_equals_profile() {
+        // the reference cases of the profile solvers
+        let cases = vec![
+            (
+                1,
+                vec![3, 1, 6, 4, 8, 9],
+                vec![4.0, 4.0, 6.0, 6.0, 8.0, 9.0],
+            ),
+            (
+                1,
+                vec![5, 5, 0, 0, 0, 0, 5, 5],
+                vec![5.0, 5.0, 2.0, 2.0, 2.0, 2.0, 5.0, 5.0],
+            ),
+            (
+                2,
+                vec![5, 5, 0, 0, 0, 0, 5, 5],
+                vec![5.0, 5.0, 4.0, 4.0, 4.0, 4.0, 5.0, 5.0],
+            ),
+            (1, vec![2, 0, 0, 2], vec![2.0; 4]),
+            (3, vec![5, 5, 0, 0, 0, 0, 5, 5], vec![5.5; 8]),
+        ];
+        for (duration, profile, expected) in cases {
+            let graph = Graph::path(&profile).unwrap();
+            let received = solve(&graph, duration, Boundary::Closed).unwrap();
+            assert!(vecf64similar(&received.levels, &expected));
+        }
+
+        // random profiles flood like the profile solvers, closed and open,
+        // in any numbering of the cells
+        let mut rng = Rng::new(5);
+        for _ in 0..1000 {
+            let size = rng.range(1, 12) as usize;
+            let profile: Vec<u64> = (0..size).map(|_| rng.range(0, 6)).collect();
+            let duration = rng.range(0, 3);
+            let graph = Graph::path(&profile).unwrap();
+            let received = solve(&graph, duration, Boundary::Closed).unwrap();
+            let problem = Problem::new(duration, &profile);
+            let expected = solutions::select_fn(&problem)(problem);
+            assert!(vecf64similar(&received.levels, &expected.levels));
+
+            let received = solve(&graph, duration, Boundary::Open).unwrap();
+            let options = crate::Options {
+                boundary: Boundary::Open,
+                ..crate::Options::default()
+            };
+            let expected = crate::solve(&profile, duration, &options).unwrap();
+            assert!(vecf64similar(&received.levels, &expected.levels));
+
+            // the same path with the cells numbered backwards and areas of 2
+            let reversed: Vec<u64> = profile.iter().rev().cloned().collect();
+            let edges: Vec<(usize, usize)> = (1..size).map(|i| (i, i - 1)).collect();
+            let graph = Graph::new(reversed, vec![2.0; size], &edges, &[]).unwrap();
+            let received = solve(&graph, duration, Boundary::Closed).unwrap();
+            let mut levels = received.levels.clone();
+            levels.reverse();
+            let problem = Problem::new(duration, &profile);
+            let expected = solutions::select_fn(&problem)(problem);
+            assert!(vecf64similar(&levels, &expected.levels));
+        }
+    }
+
+    #[test]
+    fn tanks_with_areas() {
+        // a wide tank at 0 and a narrow one at 2 behind a wall at 4: the
+        // narrow one fills first and spills into the wide one
+        let graph = Graph::new(vec![0, 4, 2], vec![4.0, 1.0, 1.0], &[(0, 1), (1, 2)], &[]).unwrap();
+        let received = solve(&graph, 2, Boundary::Closed).unwrap();
+        assert!(f64similar(received.levels[2], 4.0));
+        assert!(f64similar(received.levels[0], 2.5));
+        assert!(f64similar(graph.volume(&received), 12.0));
+
+        assert!(Graph::new(vec![1, 2], vec![1.0, 1.0], &[], &[]).is_err());
+        assert!(Graph::new(vec![1, 2], vec![1.0, 0.0], &[(0, 1)], &[]).is_err());
+        assert!(Graph::new(vec![1, 2], vec![1.0, 1.0], &[(0, 2)], &[]).is_err());
+        assert!(Graph::new(vec![], vec![], &[], &[]).is_err());
+    }
+
+    #[test]
+    fn water_conservation() {
+        // random graphs: a path through all cells plus random edges
+        let mut rng = Rng::new(17);
+        for _ in 0..300 {
+            let size = rng.range(1, 20) as usize;
+            let heights = (0..size).map(|_| rng.range(0, 8)).collect();
+            let areas = (0..size)
+                .map(|_| rng.range(1, 3) as f64)
+                .collect::<Vec<f64>>();
+            let mut edges: Vec<(usize, usize)> = (1..size).map(|i| (i - 1, i)).collect();
+            for _ in 0..size {
+                let a = rng.range(0, size as u64 - 1) as usize;
+                let b = rng.range(0, size as u64 - 1) as usize;
+                edges.push((a, b));
+            }
+            let total: f64 = areas.iter().sum();
+            let graph = Graph::new(heights, areas, &edges, &[0]).unwrap();
+            let duration = rng.range(1, 3);
+
+            let closed = solve(&graph, duration, Boundary::Closed).unwrap();
+            assert!(f64similar(graph.volume(&closed), duration as f64 * total));
+            let open = solve(&graph, duration, Boundary::Open).unwrap();
+            assert!(graph.volume(&open) <= graph.volume(&closed) + 1e-9);
+            for solution in [closed, open].iter() {
+                assert!(solution.water_covers.iter().all(|&w| w >= -1e-9));
+            }
+        }
+    }
+}
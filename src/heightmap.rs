@@ -0,0 +1,178 @@
+// module heightmap floods two-dimensional terrain: a grid of rows x cols
+// cells with a height each, connected to their four neighbours. Every cell
+// receives one unit of rain per day, like the segments of a profile. The
+// grid is a graph::Graph of unit cells whose border cells are the outlets.
+use crate::graph::{self, Graph};
+use crate::solutions::Solution;
+use crate::{Boundary, Error};
+
+// Heightmap stores heights row by row from the top left
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heightmap {
+    pub rows: usize,
+    pub cols: usize,
+    pub heights: Vec<u64>,
+}
+
+impl Heightmap {
+    pub fn new(rows: usize, cols: usize, heights: Vec<u64>) -> Result<Heightmap, Error> {
+        if rows == 0 || cols == 0 {
+            return Err(Error::EmptyProfile);
+        }
+        if rows.checked_mul(cols) != Some(heights.len()) {
+            return Err(Error::InvalidInput("heights must fill rows x cols"));
+        }
+        return Ok(Heightmap {
+            rows,
+            cols,
+            heights,
+        });
+    }
+
+    // graph joins every cell to its right and lower neighbour, border cells
+    // are the outlets. A grid is always a valid graph.
+    pub fn graph(&self) -> Graph {
+        let (rows, cols) = (self.rows, self.cols);
+        let mut edges = Vec::with_capacity(2 * rows * cols);
+        let mut outlets = Vec::new();
+        for r in 0..rows {
+            for c in 0..cols {
+                let cell = r * cols + c;
+                if c + 1 < cols {
+                    edges.push((cell, cell + 1));
+                }
+                if r + 1 < rows {
+                    edges.push((cell, cell + cols));
+                }
+                if r == 0 || c == 0 || r + 1 == rows || c + 1 == cols {
+                    outlets.push(cell);
+                }
+            }
+        }
+        let areas = vec![1.0; rows * cols];
+        return Graph::new(self.heights.clone(), areas, &edges, &outlets).unwrap();
+    }
+}
+
+// solve floods a heightmap with `duration` days of rain. The solution holds
+// levels and water covers row by row, like the heights.
+pub fn solve(map: &Heightmap, duration: u64, boundary: Boundary) -> Result<Solution, Error> {
+    return graph::solve(&map.graph(), duration, boundary);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::Rng;
+    use crate::solutions;
+    use crate::zero::{f64similar, vecf64similar};
+    use crate::Problem;
+
+    // a 5 x 5 map with a ring of 9 around a floor of 3 x 3 cells at 0
+    fn pit() -> Heightmap {
+        let mut heights = vec![9; 25];
+        for r in 1..4 {
+            for c in 1..4 {
+                heights[r * 5 + c] = 0;
+            }
+        }
+        return Heightmap::new(5, 5, heights).unwrap();
+    }
+
+    #[test]
+    fn single_pit() {
+        // closed: all rain runs into the floor
+        let received = solve(&pit(), 1, Boundary::Closed).unwrap();
+        assert!(f64similar(received.levels[12], 25.0 / 9.0));
+        assert_eq!(received.levels[0], 9.0);
+        assert!(f64similar(received.water_tot, 25.0));
+
+        // open: the ring is one flat with 16 edges to the outside and 12
+        // to the floor, the floor fills until it is full
+        let received = solve(&pit(), 1, Boundary::Open).unwrap();
+        assert!(f64similar(
+            received.levels[12],
+            (9.0 + 16.0 * 12.0 / 28.0) / 9.0
+        ));
+        let received = solve(&pit(), 20, Boundary::Open).unwrap();
+        assert!(f64similar(received.levels[12], 9.0));
+        assert!(f64similar(received.water_tot, 81.0));
+    }
+
+    #[test]
+    fn trivial_cases() {
+        let flat = Heightmap::new(2, 2, vec![4; 4]).unwrap();
+        assert_eq!(solve(&flat, 2, Boundary::Closed).unwrap().levels, [6.0; 4]);
+        assert_eq!(solve(&flat, 2, Boundary::Open).unwrap().levels, [4.0; 4]);
+        assert_eq!(
+            solve(&pit(), 0, Boundary::Closed).unwrap().levels,
+            pit()
+                .heights
+                .iter()
+                .map(|&h| h as f64)
+                .collect::<Vec<f64>>()
+        );
+        // saturation needs 81, above it the level rises evenly
+        let received = solve(&pit(), 4, Boundary::Closed).unwrap();
+        assert!(vecf64similar(&received.levels, &[9.0 + 19.0 / 25.0; 25]));
+        assert!(Heightmap::new(2, 2, vec![1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn single_row_as_profile() {
+        let mut rng = Rng::new(4);
+        for _ in 0..300 {
+            let size = rng.range(1, 12) as usize;
+            let profile: Vec<u64> = (0..size).map(|_| rng.range(0, 8)).collect();
+            let duration = rng.range(0, 3);
+            let problem = Problem::new(duration, &profile);
+            let expected = solutions::select_fn(&problem)(problem);
+            let row = Heightmap::new(1, size, profile.clone()).unwrap();
+            let column = Heightmap::new(size, 1, profile.clone()).unwrap();
+            for map in [row, column].iter() {
+                let received = solve(map, duration, Boundary::Closed).unwrap();
+                assert!(vecf64similar(&received.levels, &expected.levels));
+            }
+        }
+    }
+
+    #[test]
+    fn peaks_share_rain() {
+        // a ridge of 5 between two pits of 3 x 3, the ridge cells give half
+        // of their rain to either side
+        let mut heights = vec![9; 5 * 7];
+        for r in 1..4 {
+            for c in [1, 2, 4, 5].iter() {
+                heights[r * 7 + c] = 0;
+            }
+            heights[r * 7 + 3] = 5;
+        }
+        let map = Heightmap::new(5, 7, heights).unwrap();
+        let received = solve(&map, 1, Boundary::Closed).unwrap();
+        assert!(f64similar(received.levels[8], received.levels[12]));
+        assert!(f64similar(received.water_tot, 35.0));
+    }
+
+    #[test]
+    fn water_conservation() {
+        let mut rng = Rng::new(11);
+        for _ in 0..200 {
+            let rows = rng.range(1, 8) as usize;
+            let cols = rng.range(1, 8) as usize;
+            let heights = (0..rows * cols).map(|_| rng.range(0, 6)).collect();
+            let map = Heightmap::new(rows, cols, heights).unwrap();
+            let duration = rng.range(1, 3);
+
+            let closed = solve(&map, duration, Boundary::Closed).unwrap();
+            assert!(f64similar(
+                closed.water_tot,
+                (duration * (rows * cols) as u64) as f64
+            ));
+            let open = solve(&map, duration, Boundary::Open).unwrap();
+            assert!(open.water_tot <= closed.water_tot + 1e-9);
+            for solution in [closed, open].iter() {
+                assert!(solution.water_covers.iter().all(|&w| w >= -1e-9));
+            }
+        }
+    }
+}
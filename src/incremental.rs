@@ -0,0 +1,275 @@
+// module incremental keeps a solved problem and solves it again after local
+// edits of the terrain, such as a levee or a ditch, without touching the
+// whole profile.
+//
+// Problems are solved with the fill-and-spill model of module events. A dry
+// run of peak segments that stands above the water on both sides is never
+// crossed by water. Its rain is shared evenly by both sides, and the wells
+// between two such runs form a problem of their own. After an edit only the
+// wells between the nearest of these runs left and right of the edit are
+// simulated again, with half of the rain on the runs themselves. If the
+// water in the new state reaches a bounding run, the runs no longer isolate
+// the edit and the whole profile is simulated again.
+use crate::events::levels_at;
+use crate::solutions::Solution;
+use crate::zero::{f64equal, f64similar};
+use crate::{Error, Problem};
+
+// Solved stores the terrain, the duration of rain and the present solution.
+// land is the ground volume, max an upper bound of the highest ground; they
+// tell whether an edited profile still makes a Problem.
+#[derive(Debug)]
+pub struct Solved {
+    grounds: Vec<u64>,
+    duration: f64,
+    solution: Solution,
+    land: u128,
+    max: u64,
+}
+
+impl Solved {
+    pub fn new(problem: Problem) -> Solved {
+        let duration = problem.water_0;
+        let max = problem.ground_max;
+        let grounds = problem.grounds;
+        let levels = simulate(&grounds, duration);
+        let solution = Solution::new(levels, &grounds);
+        let land = grounds.iter().map(|&g| g as u128).sum();
+        return Solved {
+            grounds,
+            duration,
+            solution,
+            land,
+            max,
+        };
+    }
+
+    pub fn grounds(&self) -> &[u64] {
+        return &self.grounds;
+    }
+
+    pub fn solution(&self) -> &Solution {
+        return &self.solution;
+    }
+
+    // edit sets the ground of one segment to a new height and solves the
+    // affected wells again. Returns the new solution and the segments whose
+    // level changed. Fails without a change if the segment does not exist or
+    // the ground of the edited profile does not fit into u64.
+    pub fn edit(&mut self, index: usize, height: u64) -> Result<(&Solution, Vec<usize>), Error> {
+        if index >= self.grounds.len() {
+            return Err(Error::InvalidInput("segment outside the profile"));
+        }
+        self.fit(index, height)?;
+        self.grounds[index] = height;
+        let last = self.grounds.len() - 1;
+
+        let left = self.barrier(index, true);
+        let right = self.barrier(index, false);
+        let start = left.map_or(0, |(a, _)| a);
+        let end = right.map_or(last, |(_, b)| b);
+
+        // bounding runs give half their rain to the window
+        let mut rain = vec![1.0; end - start + 1];
+        for &(a, b) in left.iter().chain(right.iter()) {
+            for r in rain[a - start..=b - start].iter_mut() {
+                *r = 0.5;
+            }
+        }
+        let levels = levels_at(&self.grounds[start..=end], &rain, self.duration);
+
+        // water at the height of a bounding run spills over it
+        let reached = |run: Option<(usize, usize)>, inner: usize| {
+            return run.is_some_and(|(a, _)| levels[inner - start] >= self.grounds[a] as f64);
+        };
+        let isolated = !reached(left, left.map_or(0, |(_, b)| b + 1))
+            && !reached(right, right.map_or(0, |(a, _)| a - 1));
+        let (start, levels) = if isolated {
+            (start, levels)
+        } else {
+            (0, simulate(&self.grounds, self.duration))
+        };
+
+        let mut changed = Vec::new();
+        for (k, &level) in levels.iter().enumerate() {
+            let i = start + k;
+            let old_cover = self.solution.water_covers[i];
+            let cover = level - self.grounds[i] as f64;
+            if !f64similar(level, self.solution.levels[i]) {
+                changed.push(i);
+            }
+            self.solution.levels[i] = level;
+            self.solution.water_covers[i] = cover;
+            self.solution.water_tot += cover - old_cover;
+        }
+        return Ok((&self.solution, changed));
+    }
+
+    // fit checks that the profile with the edit makes a Problem: its ground
+    // volume and its highest ground times the number of segments fit into
+    // u64. The bound max stays above a lowered highest segment, it fitted
+    // before with the same number of segments.
+    fn fit(&mut self, index: usize, height: u64) -> Result<(), Error> {
+        let size = self.grounds.len() as u128;
+        let land = self.land - self.grounds[index] as u128 + height as u128;
+        let max = self.max.max(height);
+        if land > u64::MAX as u128 || max as u128 * size > u64::MAX as u128 {
+            return Err(Error::Overflow);
+        }
+        self.land = land;
+        self.max = max;
+        return Ok(());
+    }
+
+    // barrier finds the nearest dry run of peak segments strictly left or
+    // right of a position that stands above the water on both sides. None
+    // means the boundary is reached first.
+    fn barrier(&self, from: usize, leftwards: bool) -> Option<(usize, usize)> {
+        let grounds = &self.grounds;
+        let levels = &self.solution.levels;
+        let last = grounds.len() - 1;
+        let dry = |i: usize| f64equal(levels[i], grounds[i] as f64);
+
+        let mut i = from;
+        loop {
+            // next run in the direction of search
+            let (a, b) = if leftwards {
+                if i == 0 {
+                    return None;
+                }
+                let b = i - 1;
+                let mut a = b;
+                while a > 0 && grounds[a - 1] == grounds[b] {
+                    a -= 1;
+                }
+                (a, b)
+            } else {
+                if i >= last {
+                    return None;
+                }
+                let a = i + 1;
+                let mut b = a;
+                while b < last && grounds[b + 1] == grounds[a] {
+                    b += 1;
+                }
+                (a, b)
+            };
+            if a == 0 || b == last {
+                return None;
+            }
+
+            let g = grounds[a] as f64;
+            let isolating = (a..=b).all(dry) && levels[a - 1] < g && levels[b + 1] < g;
+            if isolating {
+                return Some((a, b));
+            }
+            i = if leftwards { a } else { b };
+        }
+    }
+}
+
+// simulate floods the whole profile, one unit of rain per segment and day
+fn simulate(grounds: &[u64], duration: f64) -> Vec<f64> {
+    let rain = vec![1.0; grounds.len()];
+    return levels_at(grounds, &rain, duration);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zero::vecf64similar;
+
+    // the edited profile solved from scratch
+    fn full_solve(duration: u64, grounds: &[u64]) -> Vec<f64> {
+        return simulate(grounds, duration as f64);
+    }
+
+    #[test]
+    fn edit_single_well() {
+        let mut solved = Solved::new(Problem::new(2, &[0, 6, 0, 6, 0, 6, 0]));
+        let (solution, changed) = solved.edit(2, 1).unwrap();
+        let expected = full_solve(2, &[0, 6, 1, 6, 0, 6, 0]);
+        assert!(vecf64similar(&solution.levels, &expected));
+        assert_eq!(changed, vec![2]);
+        assert!(f64similar(solution.water_tot, 14.0));
+    }
+
+    #[test]
+    fn edit_overtops_barrier() {
+        // raising the floor of the well right of the 6 makes it spill over
+        // the 6, the whole profile is solved again
+        let mut solved = Solved::new(Problem::new(1, &[0, 8, 0, 6, 2, 9, 0, 0]));
+        let (solution, changed) = solved.edit(4, 5).unwrap();
+        let expected = full_solve(1, &[0, 8, 0, 6, 5, 9, 0, 0]);
+        assert!(vecf64similar(&solution.levels, &expected));
+        assert_eq!(changed, vec![2, 4]);
+    }
+
+    #[test]
+    fn edit_peak() {
+        let grounds = vec![3, 1, 6, 4, 8, 9];
+        let mut solved = Solved::new(Problem::new(1, &grounds));
+        let (solution, _) = solved.edit(2, 2).unwrap();
+        let expected = full_solve(1, &[3, 1, 2, 4, 8, 9]);
+        assert!(vecf64similar(&solution.levels, &expected));
+        assert_eq!(solved.grounds(), &[3, 1, 2, 4, 8, 9]);
+    }
+
+    #[test]
+    fn invalid_edits() {
+        let mut solved = Solved::new(Problem::new(1, &[3, 1, 6]));
+        assert!(solved.edit(3, 2).is_err());
+        let half = u64::MAX / 2;
+        assert_eq!(solved.edit(1, half).err(), Some(Error::Overflow));
+        assert_eq!(solved.grounds(), &[3, 1, 6]);
+
+        let mut solved = Solved::new(Problem::new(0, &[half, 0]));
+        assert!(solved.edit(1, half).is_ok());
+        assert_eq!(solved.edit(0, half + 2).err(), Some(Error::Overflow));
+        assert!(solved.edit(0, 0).is_ok());
+    }
+
+    #[test]
+    fn edits_equal_fresh_solves() {
+        // every edit of every profile of up to five segments of heights 0
+        // to 3, against the edited profile solved from scratch
+        for size in 1..=5u32 {
+            for code in 0..4u64.pow(size) {
+                let grounds: Vec<u64> = (0..size).map(|k| code / 4u64.pow(k) % 4).collect();
+                for duration in 0..3 {
+                    let before = full_solve(duration, &grounds);
+                    for index in 0..size as usize {
+                        for height in 0..4 {
+                            let mut solved = Solved::new(Problem::new(duration, &grounds));
+                            let (solution, changed) = solved.edit(index, height).unwrap();
+                            let mut edited = grounds.clone();
+                            edited[index] = height;
+                            let expected = full_solve(duration, &edited);
+                            assert!(vecf64similar(&solution.levels, &expected));
+                            let water = (duration * size as u64) as f64;
+                            assert!(f64similar(solution.water_tot, water));
+                            for (i, &level) in before.iter().enumerate() {
+                                let moved = !f64similar(level, expected[i]);
+                                assert_eq!(changed.contains(&i), moved);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn successive_edits() {
+        // a levee rises and falls along a long profile
+        let mut grounds: Vec<u64> = (0..40).map(|i| (i * 7 % 11) as u64).collect();
+        let mut solved = Solved::new(Problem::new(2, &grounds));
+        for k in 0..400 {
+            let index = k * 13 % grounds.len();
+            let height = (k * 5 % 14) as u64;
+            grounds[index] = height;
+            let (solution, _) = solved.edit(index, height).unwrap();
+            assert!(vecf64similar(&solution.levels, &full_solve(2, &grounds)));
+        }
+    }
+}
@@ -1,47 +1,458 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::fs;
+use std::str::FromStr;
 use toml;
+
+use crate::alerts::Location;
+use crate::damage::Damage;
+use crate::dem::{self, Datum, Grid, Line};
+
+// Command is the first argument, solving is the default
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Solve,
+    Generate,
+    Stats,
+    Lakes,
+    Damage,
+}
+
+// Config holds the command, positional arguments and `--key value` options
 pub struct Config {
+    pub command: Command,
     pub filename: String,
+    pub positional: Vec<String>,
+    pub options: HashMap<String, String>,
 }
 
+// Rawinput is the contents of an input file and how to read it. Plain
+// text and csv files hold no duration, it comes from --duration, which
+// overrides the one of other files. column and x_column select csv columns
+// by header name or by number, counted from 1. line is the transect
+// through an elevation grid, its heights are rounded to units of resolution.
+#[derive(Default)]
 pub struct Rawinput {
     pub contents: String,
+    pub format: Format,
+    pub duration: Option<u64>,
+    pub column: Option<String>,
+    pub x_column: Option<String>,
+    pub line: Option<Line>,
+    pub resolution: Option<f64>,
 }
 
-#[derive(Deserialize, Debug)]
+// Format of an input file, given by --input-format or the file extension,
+// toml if neither tells
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Format {
+    #[default]
+    Toml,
+    Yaml,
+    Json,
+    Text,
+    Csv,
+    Asc,
+}
+
+// Data is the problem read from a file. widths, if given, are the positive
+// widths of the entries of the profile, which are weights and need not be
+// whole numbers. datum maps heights of a transect back to elevations.
+// locations are named points of interest at entries of the profile, from a
+// [locations] table. damage, if given, is one per entry of the profile and
+// per unit of its width.
+#[derive(Deserialize, Serialize, Debug)]
 pub struct Data {
     pub duration: u64,
     pub profile: Vec<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub widths: Option<Vec<f64>>,
+    #[serde(skip)]
+    pub datum: Option<Datum>,
+    // toml needs the locations with a critical depth, which are tables,
+    // after the others
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        serialize_with = "toml::ser::tables_last"
+    )]
+    pub locations: BTreeMap<String, Location>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub damage: Option<Vec<Damage>>,
 }
 
 // get config from command line arguments
+// rain [command] [positional ..] [--key value | --key=value ..]
+// the first positional argument is the filename
 impl Config {
+    // new reads the arguments like try_new but does not fail, an option
+    // without a value is kept with an empty value and fails when it is parsed
     pub fn new(args: &[String], default: &'static str) -> Config {
-        let filename = if args.len() > 1 {
-            args[1].clone()
-        } else {
-            default.to_string()
+        return Config::read(args, default).0;
+    }
+
+    // try_new reads the command, positional arguments and options. Options
+    // are `--key value` or `--key=value`, a `--key` at the end or followed
+    // by another option has no value and is an error.
+    pub fn try_new(args: &[String], default: &'static str) -> Result<Config, Box<dyn Error>> {
+        return match Config::read(args, default) {
+            (config, None) => Ok(config),
+            (_, Some(flag)) => Err(format!("--{} needs a value", flag).into()),
+        };
+    }
+
+    // read returns the config and the first option without a value
+    fn read(args: &[String], default: &'static str) -> (Config, Option<String>) {
+        let (command, skip) = match args.get(1).map(|a| a.as_str()) {
+            Some("generate") => (Command::Generate, 2),
+            Some("solve") => (Command::Solve, 2),
+            Some("stats") => (Command::Stats, 2),
+            Some("lakes") => (Command::Lakes, 2),
+            Some("damage") => (Command::Damage, 2),
+            _ => (Command::Solve, 1),
+        };
+        let mut rest = args.iter().skip(skip).peekable();
+
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+        let mut missing = None;
+        while let Some(arg) = rest.next() {
+            match arg.strip_prefix("--") {
+                Some(flag) => match flag.split_once('=') {
+                    Some((key, value)) => {
+                        options.insert(key.to_string(), value.to_string());
+                    }
+                    None => {
+                        let value = rest.next_if(|value| !value.starts_with("--"));
+                        if value.is_none() && missing.is_none() {
+                            missing = Some(flag.to_string());
+                        }
+                        options.insert(flag.to_string(), value.cloned().unwrap_or_default());
+                    }
+                },
+                None => positional.push(arg.clone()),
+            }
+        }
+
+        let filename = match positional.first() {
+            Some(name) => name.clone(),
+            None => default.to_string(),
+        };
+        let config = Config {
+            command,
+            filename,
+            positional,
+            options,
+        };
+        return (config, missing);
+    }
+
+    // option parses the value of `--key`, None if it is not given
+    pub fn option<T>(&self, key: &str) -> Result<Option<T>, Box<dyn Error>>
+    where
+        T: FromStr,
+        T::Err: Error + 'static,
+    {
+        return match self.options.get(key) {
+            Some(value) => match value.parse() {
+                Ok(parsed) => Ok(Some(parsed)),
+                Err(err) => Err(format!("--{} {}: {}", key, value, err).into()),
+            },
+            None => Ok(None),
+        };
+    }
+}
+
+impl Format {
+    pub fn from_filename(filename: &str) -> Option<Format> {
+        let extension = filename.rsplit_once('.')?.1;
+        return extension.parse().ok();
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Format, String> {
+        return match name.to_ascii_lowercase().as_str() {
+            "toml" => Ok(Format::Toml),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            "json" => Ok(Format::Json),
+            "txt" | "text" => Ok(Format::Text),
+            "csv" => Ok(Format::Csv),
+            "asc" => Ok(Format::Asc),
+            _ => Err(format!("unknown input format {}", name)),
         };
-        Config { filename }
     }
 }
 
 // read file with input data
 impl Rawinput {
     pub fn new(config: Config) -> Result<Rawinput, Box<dyn Error>> {
+        let format = match config.options.get("input-format") {
+            Some(name) => name.parse()?,
+            None => Format::from_filename(&config.filename).unwrap_or(Format::Toml),
+        };
+        let duration = config.option("duration")?;
+        let column = config.options.get("column").cloned();
+        let x_column = config.options.get("x-column").cloned();
+        let line = transect_line(&config)?;
+        let resolution = config.option("resolution")?;
         let contents = fs::read_to_string(config.filename)?;
-        Ok(Rawinput { contents })
+        Ok(Rawinput {
+            contents,
+            format,
+            duration,
+            column,
+            x_column,
+            line,
+            resolution,
+        })
     }
 }
 
+// transect_line reads --row R, --col C or --path R,C;R,C;.. of a grid
+fn transect_line(config: &Config) -> Result<Option<Line>, Box<dyn Error>> {
+    if let Some(row) = config.option("row")? {
+        return Ok(Some(Line::Row(row)));
+    }
+    if let Some(col) = config.option("col")? {
+        return Ok(Some(Line::Column(col)));
+    }
+    let path = match config.options.get("path") {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let mut vertices = Vec::new();
+    for vertex in path.split(';').filter(|v| !v.trim().is_empty()) {
+        let cell = vertex
+            .split_once(',')
+            .and_then(|(r, c)| Some((r.trim().parse().ok()?, c.trim().parse().ok()?)));
+        match cell {
+            Some(cell) => vertices.push(cell),
+            None => return Err(format!("--path vertex {} is not row,col", vertex).into()),
+        }
+    }
+    return Ok(Some(Line::Polyline(vertices)));
+}
+
 // deserialize raw input data
 impl Data {
     pub fn new(rawinput: Rawinput) -> Result<Data, Box<dyn Error>> {
-        let parsed: Data = toml::from_str(&rawinput.contents)?;
+        let contents = &rawinput.contents;
+        let mut parsed: Data = match rawinput.format {
+            Format::Toml => toml::from_str(contents)?,
+            #[cfg(feature = "yaml")]
+            Format::Yaml => serde_yaml::from_str(contents)?,
+            #[cfg(feature = "json")]
+            Format::Json => serde_json::from_str(contents)?,
+            Format::Text => Data {
+                duration: 0,
+                profile: parse_text(contents)?,
+                widths: None,
+                datum: None,
+                locations: BTreeMap::new(),
+                damage: None,
+            },
+            Format::Asc => {
+                let line = match &rawinput.line {
+                    Some(line) => line,
+                    None => return Err("grid input needs --row, --col or --path".into()),
+                };
+                let elevations = Grid::parse(contents)?.transect(line)?;
+                let resolution = rawinput.resolution.unwrap_or(1.0);
+                if resolution.is_nan() || resolution <= 0.0 {
+                    return Err("--resolution must be positive".into());
+                }
+                let (profile, datum) = dem::to_profile(&elevations, resolution);
+                Data {
+                    duration: 0,
+                    profile,
+                    widths: None,
+                    datum: Some(datum),
+                    locations: BTreeMap::new(),
+                    damage: None,
+                }
+            }
+            Format::Csv => {
+                let column = rawinput.column.as_deref().unwrap_or("1");
+                parse_csv(contents, column, rawinput.x_column.as_deref())?
+            }
+            #[allow(unreachable_patterns)]
+            format => return Err(format!("built without {:?} input", format).into()),
+        };
+
+        match (rawinput.duration, rawinput.format) {
+            (Some(duration), _) => parsed.duration = duration,
+            (None, Format::Text) | (None, Format::Csv) | (None, Format::Asc) => {
+                return Err("--duration is required for text, csv and grid input".into())
+            }
+            _ => {}
+        }
+        if parsed.profile.is_empty() {
+            return Err("profile is empty".into());
+        }
+        if let Some(widths) = &parsed.widths {
+            let positive = widths.iter().all(|&w| w.is_finite() && w > 0.0);
+            if widths.len() != parsed.profile.len() || !positive {
+                return Err("widths must be positive, one per profile entry".into());
+            }
+        }
+        if let Some(damage) = &parsed.damage {
+            if damage.len() != parsed.profile.len() {
+                return Err("damage must have one entry per profile entry".into());
+            }
+        }
+        for (name, location) in parsed.locations.iter() {
+            if location.position >= parsed.profile.len() {
+                return Err(format!("location {} is outside the profile", name).into());
+            }
+        }
         Ok(parsed)
     }
+
+    // serialize to the toml layout `new` reads
+    pub fn to_toml(&self) -> Result<String, Box<dyn Error>> {
+        Ok(toml::to_string(self)?)
+    }
+}
+
+// parse_text reads heights separated by whitespace or newlines, lines
+// starting with # are comments
+fn parse_text(contents: &str) -> Result<Vec<u64>, Box<dyn Error>> {
+    let mut profile = Vec::new();
+    for line in contents
+        .lines()
+        .filter(|l| !l.trim_start().starts_with('#'))
+    {
+        for word in line.split_whitespace() {
+            let height = word
+                .parse()
+                .map_err(|err| format!("height {}: {}", word, err))?;
+            profile.push(height);
+        }
+    }
+    return Ok(profile);
+}
+
+// parse_csv reads the profile from a column of comma separated values. A first
+// line whose height field is not a number is the header. Optional x
+// coordinates are the left edges of the segments, a segment reaches to the
+// next x, the last one is as wide as the one before.
+fn parse_csv(contents: &str, column: &str, x_column: Option<&str>) -> Result<Data, Box<dyn Error>> {
+    let rows: Vec<Vec<String>> = contents
+        .lines()
+        .filter(|l| !l.trim().is_empty() && !l.trim_start().starts_with('#'))
+        .map(csv_fields)
+        .collect::<Result<_, _>>()?;
+    if rows.is_empty() {
+        return Err("csv file has no rows".into());
+    }
+
+    // find a column by name in the header or by number
+    let header = &rows[0];
+    let index = |name: &str| -> Result<usize, Box<dyn Error>> {
+        if let Ok(number) = name.parse::<usize>() {
+            if number == 0 {
+                return Err("csv columns are counted from 1".into());
+            }
+            return Ok(number - 1);
+        }
+        return match header.iter().position(|field| field == name) {
+            Some(i) => Ok(i),
+            None => Err(format!("no csv column {}", name).into()),
+        };
+    };
+    let column = index(column)?;
+    let x_column = x_column.map(index).transpose()?;
+
+    fn field<T>(row: &[String], i: usize) -> Result<T, Box<dyn Error>>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        let value = row.get(i).ok_or("csv row is too short")?;
+        return Ok(value
+            .parse()
+            .map_err(|err| format!("csv value {}: {}", value, err))?);
+    }
+    let has_header = header.get(column).is_none_or(|f| f.parse::<u64>().is_err());
+    let body = &rows[has_header as usize..];
+
+    let profile = body
+        .iter()
+        .map(|row| field(row, column))
+        .collect::<Result<Vec<u64>, _>>()?;
+    let widths = match x_column {
+        Some(i) => {
+            let xs = body
+                .iter()
+                .map(|row| field(row, i))
+                .collect::<Result<Vec<f64>, _>>()?;
+            Some(widths_from(&xs)?)
+        }
+        None => None,
+    };
+    return Ok(Data {
+        duration: 0,
+        profile,
+        widths,
+        datum: None,
+        locations: BTreeMap::new(),
+        damage: None,
+    });
+}
+
+// csv_fields splits a line of csv at commas outside of double quotes. Quotes
+// around a field are removed, two double quotes inside one stand for a
+// quote; fields are trimmed outside the quotes. A field cannot span lines.
+fn csv_fields(line: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut field = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err(format!("csv quote not closed: {}", line).into()),
+                }
+            }
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            if chars.peek().is_some_and(|&c| c != ',') {
+                return Err(format!("csv text after a quoted field: {}", line).into());
+            }
+        } else {
+            while let Some(c) = chars.next_if(|&c| c != ',') {
+                field.push(c);
+            }
+            field.truncate(field.trim_end().len());
+        }
+        fields.push(field);
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+// widths of segments from their left edges
+fn widths_from(xs: &[f64]) -> Result<Vec<f64>, Box<dyn Error>> {
+    if xs.iter().any(|x| !x.is_finite()) {
+        return Err("x coordinates must be finite".into());
+    }
+    let mut widths = Vec::with_capacity(xs.len());
+    for pair in xs.windows(2) {
+        if pair[1] <= pair[0] {
+            return Err("x coordinates must increase".into());
+        }
+        widths.push(pair[1] - pair[0]);
+    }
+    widths.push(*widths.last().unwrap_or(&1.0));
+    return Ok(widths);
 }
 
 #[cfg(test)]
@@ -65,6 +476,74 @@ mod tests {
         assert_eq!(received, expected);
     }
 
+    #[test]
+    fn command_and_options() {
+        let a: Vec<String> = ["rain", "generate", "walk", "--seed", "7", "--high=20"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let config = Config::new(&a, "example.toml");
+        assert_eq!(config.command, Command::Generate);
+        assert_eq!(config.positional, ["walk"]);
+        assert_eq!(config.option::<u64>("seed").unwrap(), Some(7));
+        assert_eq!(config.option::<u64>("high").unwrap(), Some(20));
+        assert_eq!(config.option::<u64>("low").unwrap(), None);
+        assert!(config.option::<f64>("seed").is_ok());
+
+        let a: Vec<String> = ["rain", "--seed", "x"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let config = Config::new(&a, "example.toml");
+        assert_eq!(config.command, Command::Solve);
+        assert_eq!(config.filename, "example.toml");
+        assert!(config.option::<u64>("seed").is_err());
+    }
+
+    #[test]
+    fn option_without_value() {
+        let args = |a: &[&str]| -> Vec<String> { a.iter().map(|s| s.to_string()).collect() };
+        let trailing = args(&["rain", "solve", "profile.toml", "--seed"]);
+        assert!(Config::try_new(&trailing, "example.toml").is_err());
+        let config = Config::new(&trailing, "example.toml");
+        assert!(config.option::<u64>("seed").is_err());
+
+        let followed = args(&["rain", "--seed", "--high", "20", "a.toml"]);
+        match Config::try_new(&followed, "example.toml") {
+            Err(err) => assert_eq!(err.to_string(), "--seed needs a value"),
+            Ok(_) => panic!("an option without value is accepted"),
+        }
+        let config = Config::new(&followed, "example.toml");
+        assert_eq!(config.option::<u64>("high").unwrap(), Some(20));
+        assert_eq!(config.filename, "a.toml");
+
+        let valid = args(&["rain", "--low", "-3", "--name=", "a.toml"]);
+        let config = Config::try_new(&valid, "example.toml").unwrap();
+        assert_eq!(config.option::<i64>("low").unwrap(), Some(-3));
+        assert_eq!(config.options["name"], "");
+        assert_eq!(config.filename, "a.toml");
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let data = Data {
+            duration: 2,
+            profile: vec![3, 4, 0],
+            widths: None,
+            datum: None,
+            locations: BTreeMap::new(),
+            damage: None,
+        };
+        let a = Rawinput {
+            contents: data.to_toml().unwrap(),
+            format: Format::Toml,
+            ..Default::default()
+        };
+        let received = Data::new(a).unwrap();
+        assert_eq!(received.duration, 2);
+        assert_eq!(received.profile, [3, 4, 0]);
+    }
+
     #[test]
     fn parse_toml() {
         let a = Rawinput {
@@ -73,9 +552,214 @@ mod tests {
                 profile = [ 3, 4, 0 ]
                 "#
             .to_string(),
+            format: Format::Toml,
+            ..Default::default()
         };
         let expected = Data::new(a).unwrap();
         assert_eq!(expected.duration, 5);
         assert_eq!(expected.profile, [3, 4, 0]);
     }
+
+    #[test]
+    fn parse_locations() {
+        let a = Rawinput {
+            contents: r#"
+                duration = 1
+                profile = [ 3, 4, 0 ]
+                [locations]
+                village = 2
+                road = { position = 1, critical = 0.5 }
+                "#
+            .to_string(),
+            format: Format::Toml,
+            ..Default::default()
+        };
+        let received = Data::new(a).unwrap();
+        let at = |position, critical| Location { position, critical };
+        assert_eq!(received.locations["village"], at(2, None));
+        assert_eq!(received.locations["road"], at(1, Some(0.5)));
+        let round_trip = Rawinput {
+            contents: received.to_toml().unwrap(),
+            format: Format::Toml,
+            ..Default::default()
+        };
+        assert_eq!(Data::new(round_trip).unwrap().locations, received.locations);
+
+        let a = Rawinput {
+            contents: "duration = 1\nprofile = [3]\n[locations]\nfar = 1\n".to_string(),
+            format: Format::Toml,
+            ..Default::default()
+        };
+        assert!(Data::new(a).is_err());
+    }
+
+    #[test]
+    fn parse_damage() {
+        let a = Rawinput {
+            contents: "duration = 1\nprofile = [3, 0]\ndamage = [5, [[1.0, 2.5]]]\n".to_string(),
+            format: Format::Toml,
+            ..Default::default()
+        };
+        let received = Data::new(a).unwrap().damage.unwrap();
+        assert_eq!(
+            received,
+            [
+                Damage::value(5.0).unwrap(),
+                Damage::curve(vec![(1.0, 2.5)]).unwrap()
+            ]
+        );
+
+        // damages are checked when they are read
+        for damage in ["[5, [[1.0, 2.5], [1.0, 3.0]]]", "[nan, 0]"].iter() {
+            let a = Rawinput {
+                contents: format!("duration = 1\nprofile = [3, 0]\ndamage = {}\n", damage),
+                format: Format::Toml,
+                ..Default::default()
+            };
+            assert!(Data::new(a).is_err());
+        }
+
+        let a = Rawinput {
+            contents: "duration = 1\nprofile = [3, 0]\ndamage = [5]\n".to_string(),
+            format: Format::Toml,
+            ..Default::default()
+        };
+        assert!(Data::new(a).is_err());
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn parse_yaml() {
+        let a = Rawinput {
+            contents: "duration: 5\nprofile: [3, 4, 0]\n".to_string(),
+            format: Format::Yaml,
+            ..Default::default()
+        };
+        let expected = Data::new(a).unwrap();
+        assert_eq!(expected.duration, 5);
+        assert_eq!(expected.profile, [3, 4, 0]);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn parse_json() {
+        let a = Rawinput {
+            contents: r#"{ "duration": 5, "profile": [3, 4, 0] }"#.to_string(),
+            format: Format::Json,
+            ..Default::default()
+        };
+        let expected = Data::new(a).unwrap();
+        assert_eq!(expected.duration, 5);
+        assert_eq!(expected.profile, [3, 4, 0]);
+    }
+
+    #[test]
+    fn input_format() {
+        assert_eq!(Format::from_filename("example.yaml"), Some(Format::Yaml));
+        assert_eq!(Format::from_filename("a.b/foo.YML"), Some(Format::Yaml));
+        assert_eq!(Format::from_filename("foo.json"), Some(Format::Json));
+        assert_eq!(Format::from_filename("example.toml"), Some(Format::Toml));
+        assert_eq!(Format::from_filename("profile"), None);
+        assert_eq!(Format::from_filename("profile.csv"), Some(Format::Csv));
+        assert_eq!(Format::from_filename("profile.txt"), Some(Format::Text));
+        assert!("xml".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn parse_plain_text() {
+        let a = Rawinput {
+            contents: "# heights\n3 4\n0\n\n5\t1\n".to_string(),
+            format: Format::Text,
+            duration: Some(2),
+            ..Default::default()
+        };
+        let received = Data::new(a).unwrap();
+        assert_eq!(received.duration, 2);
+        assert_eq!(received.profile, [3, 4, 0, 5, 1]);
+
+        let a = Rawinput {
+            contents: "3 4 0".to_string(),
+            format: Format::Text,
+            ..Default::default()
+        };
+        assert!(Data::new(a).is_err());
+    }
+
+    #[test]
+    fn parse_csv_columns() {
+        let contents = "x,name,height\n0,a,3\n2,b,1\n3,c,6\n".to_string();
+        let a = Rawinput {
+            contents: contents.clone(),
+            format: Format::Csv,
+            duration: Some(1),
+            column: Some("height".to_string()),
+            x_column: Some("1".to_string()),
+            ..Default::default()
+        };
+        let received = Data::new(a).unwrap();
+        assert_eq!(received.profile, [3, 1, 6]);
+        assert_eq!(received.widths, Some(vec![2.0, 1.0, 1.0]));
+
+        // no header, first column by default
+        let a = Rawinput {
+            contents: "7,1\n8,2\n".to_string(),
+            format: Format::Csv,
+            duration: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(Data::new(a).unwrap().profile, [7, 8]);
+
+        // quoted fields and fractional x-coordinates
+        let a = Rawinput {
+            contents: "\"x, m\",\"say \"\"hi\"\"\",h\n0.5,\"a,b\",3\n1.25, \"\" ,1\n".to_string(),
+            format: Format::Csv,
+            duration: Some(1),
+            column: Some("h".to_string()),
+            x_column: Some("x, m".to_string()),
+            ..Default::default()
+        };
+        let received = Data::new(a).unwrap();
+        assert_eq!(received.profile, [3, 1]);
+        assert_eq!(received.widths, Some(vec![0.75, 0.75]));
+        assert_eq!(
+            csv_fields("\"say \"\"hi\"\"\", b ,").unwrap(),
+            ["say \"hi\"", "b", ""]
+        );
+        assert!(csv_fields("\"open,1").is_err());
+        assert!(csv_fields("\"a\"b,1").is_err());
+
+        let a = Rawinput {
+            contents,
+            format: Format::Csv,
+            duration: Some(1),
+            column: Some("name".to_string()),
+            ..Default::default()
+        };
+        assert!(Data::new(a).is_err());
+    }
+
+    #[test]
+    fn grid_transect() {
+        let grid = "ncols 3\nnrows 2\nxllcorner 0\nyllcorner 0\ncellsize 5\n\
+                    12.5 10.0 13.0\n11.0 14.5 10.5\n";
+        let a = Rawinput {
+            contents: grid.to_string(),
+            format: Format::Asc,
+            duration: Some(1),
+            line: Some(Line::Polyline(vec![(1, 0), (0, 1), (1, 2)])),
+            resolution: Some(0.5),
+            ..Default::default()
+        };
+        let received = Data::new(a).unwrap();
+        assert_eq!(received.profile, [2, 0, 1]);
+        assert_eq!(received.datum.unwrap().elevation(1.0), 10.5);
+
+        let a = Rawinput {
+            contents: grid.to_string(),
+            format: Format::Asc,
+            duration: Some(1),
+            ..Default::default()
+        };
+        assert!(Data::new(a).is_err());
+    }
 }
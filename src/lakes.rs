@@ -0,0 +1,161 @@
+// module lakes reads the lakes off a solution: maximal runs of wet segments
+// that share one surface level, with their extent, depths and volume and
+// what holds them at either side.
+use crate::solutions::Solution;
+use crate::zero::{f64equal, f64similar};
+
+// Bound of a lake at one side
+// Wall: the end of the profile
+// Shore: dry ground higher than the surface, the segment next to the lake
+// Peak: dry ground at the height of the surface, the lake is full to the
+// brim and spills over it when more water comes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bound {
+    Wall,
+    Shore(usize),
+    Peak(usize),
+}
+
+// Lake describes one lake, start and end are the first and last wet
+// segment, width is their number. Depths are measured from the surface to
+// the ground of the segments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lake {
+    pub start: usize,
+    pub end: usize,
+    pub level: f64,
+    pub max_depth: f64,
+    pub mean_depth: f64,
+    pub volume: f64,
+    pub width: usize,
+    pub left: Bound,
+    pub right: Bound,
+}
+
+// lakes lists the lakes of a solution of grounds from left to right
+pub fn lakes(grounds: &[u64], solution: &Solution) -> Vec<Lake> {
+    let levels = &solution.levels;
+    let wet = |i: usize| !f64equal(levels[i], grounds[i] as f64) && levels[i] > grounds[i] as f64;
+
+    let mut lakes: Vec<Lake> = Vec::new();
+    let mut i = 0;
+    while i < grounds.len() {
+        if !wet(i) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i + 1 < grounds.len() && wet(i + 1) && f64similar(levels[i + 1], levels[start]) {
+            i += 1;
+        }
+        let end = i;
+        i += 1;
+
+        let width = end - start + 1;
+        let depths = (start..=end).map(|j| levels[j] - grounds[j] as f64);
+        let volume: f64 = depths.clone().sum();
+        let level = levels[start];
+        lakes.push(Lake {
+            start,
+            end,
+            level,
+            max_depth: depths.fold(0.0, f64::max),
+            mean_depth: volume / width as f64,
+            volume,
+            width,
+            left: bound(grounds, level, start.checked_sub(1)),
+            right: bound(grounds, level, Some(end + 1)),
+        });
+    }
+    return lakes;
+}
+
+// bound tells what holds a lake at level on the side of segment i
+fn bound(grounds: &[u64], level: f64, i: Option<usize>) -> Bound {
+    return match i {
+        Some(i) if i < grounds.len() => {
+            if f64equal(grounds[i] as f64, level) {
+                Bound::Peak(i)
+            } else {
+                Bound::Shore(i)
+            }
+        }
+        _ => Bound::Wall,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::Rng;
+    use crate::solutions;
+    use crate::Problem;
+
+    #[test]
+    fn two_lakes() {
+        // the left well is full and spills over the peak of 4 at 3 into
+        // the right one
+        let grounds = [6, 0, 1, 4, 0, 0, 9];
+        let levels = vec![6.0, 4.0, 4.0, 4.0, 2.5, 2.5, 9.0];
+        let received = lakes(&grounds, &Solution::new(levels, &grounds));
+        assert_eq!(received.len(), 2);
+        let left = &received[0];
+        assert_eq!((left.start, left.end, left.width), (1, 2, 2));
+        assert_eq!(
+            (left.level, left.max_depth, left.mean_depth),
+            (4.0, 4.0, 3.5)
+        );
+        assert_eq!(left.volume, 7.0);
+        assert_eq!((left.left, left.right), (Bound::Shore(0), Bound::Peak(3)));
+        let right = &received[1];
+        assert_eq!((right.start, right.end, right.volume), (4, 5, 5.0));
+        assert_eq!(
+            (right.left, right.right),
+            (Bound::Shore(3), Bound::Shore(6))
+        );
+
+        // at saturation the lakes reach the walls and the brim of the peak
+        let grounds = [0, 3, 0];
+        let received = lakes(&grounds, &Solution::new(vec![3.0; 3], &grounds));
+        assert_eq!(received.len(), 2);
+        assert_eq!(
+            (received[0].left, received[0].right),
+            (Bound::Wall, Bound::Peak(1))
+        );
+        assert_eq!(
+            (received[1].left, received[1].right),
+            (Bound::Peak(1), Bound::Wall)
+        );
+
+        let dry = Solution::new(vec![1.0, 2.0], &[1, 2]);
+        assert!(lakes(&[1, 2], &dry).is_empty());
+    }
+
+    #[test]
+    fn volumes_add_up() {
+        let mut rng = Rng::new(8);
+        for _ in 0..1000 {
+            let size = rng.range(1, 20) as usize;
+            let grounds: Vec<u64> = (0..size).map(|_| rng.range(0, 9)).collect();
+            let problem = Problem::new(rng.range(0, 3), &grounds);
+            let solution = solutions::select_fn(&problem)(problem);
+            let received = lakes(&grounds, &solution);
+            let volume: f64 = received.iter().map(|l| l.volume).sum();
+            assert!(f64similar(volume, solution.water_tot));
+            for lake in received.iter() {
+                assert!(lake.max_depth >= lake.mean_depth);
+                assert!((lake.start..=lake.end).all(|i| f64similar(solution.levels[i], lake.level)));
+                for bound in [lake.left, lake.right].iter() {
+                    let dry = |i: usize| f64equal(solution.levels[i], grounds[i] as f64);
+                    match *bound {
+                        Bound::Shore(i) => assert!(dry(i) && grounds[i] as f64 > lake.level),
+                        Bound::Peak(i) => {
+                            assert!(dry(i) && f64similar(grounds[i] as f64, lake.level))
+                        }
+                        Bound::Wall => (),
+                    }
+                }
+            }
+        }
+    }
+}
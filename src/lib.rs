@@ -1,10 +1,40 @@
+#![allow(clippy::needless_return)]
+
+pub mod alerts;
 pub mod algorithm;
+pub mod damage;
+pub mod dem;
+pub mod events;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod generate;
+pub mod graph;
+pub mod heightmap;
+pub mod incremental;
+#[cfg(feature = "toml")]
 pub mod input;
+pub mod lakes;
+pub mod piecewise;
+pub mod runs;
+pub mod sloped;
 pub mod solutions;
+pub mod stats;
+pub mod tree;
+pub mod wells;
 pub mod zero;
 
-// Problem collects data and characterises problem
-#[derive(Debug)]
+use std::fmt;
+
+use solutions::Solution;
+
+// Problem collects data and characterises problem. With serde it is written
+// as its duration and grounds only, reading it computes the rest again.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "RawProblem", into = "RawProblem")
+)]
 pub struct Problem {
     pub water_0: f64,      //initial water level on each segment
     pub grounds: Vec<u64>, //ground level
@@ -16,13 +46,93 @@ pub struct Problem {
     saturation_water: u64,
 }
 
+// RawProblem is how a problem is serialized
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RawProblem {
+    duration: u64,
+    grounds: Vec<u64>,
+}
+
+#[cfg(feature = "serde")]
+impl std::convert::TryFrom<RawProblem> for Problem {
+    type Error = Error;
+
+    fn try_from(raw: RawProblem) -> Result<Problem, Error> {
+        return Problem::try_new(raw.duration, &raw.grounds);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Problem> for RawProblem {
+    fn from(problem: Problem) -> RawProblem {
+        return RawProblem {
+            duration: problem.water_0 as u64,
+            grounds: problem.grounds,
+        };
+    }
+}
+
+// Error of the fallible entry points of the library
+// EmptyProfile: the profile has no segment
+// Overflow: amounts of water or ground that do not fit into u64
+// InvalidInput: the arguments do not describe a valid input
+// Unsupported: the options cannot be combined
+// Conservation: the solution lost or gained water beyond the tolerance
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    EmptyProfile,
+    Overflow,
+    InvalidInput(&'static str),
+    Unsupported(&'static str),
+    Conservation { expected: f64, received: f64 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Error::EmptyProfile => write!(f, "profile is empty"),
+            Error::Overflow => write!(f, "amount of water exceeds u64"),
+            Error::InvalidInput(what) => write!(f, "invalid input: {}", what),
+            Error::Unsupported(what) => write!(f, "unsupported: {}", what),
+            Error::Conservation { expected, received } => write!(
+                f,
+                "water not conserved: expected {}, received {}",
+                expected, received
+            ),
+        };
+    }
+}
+
+impl std::error::Error for Error {}
+
 // Problem implementation processes input data and gets charactistics of it
 impl Problem {
+    // try_new checks what new would panic on: an empty profile and amounts
+    // of water that do not fit into u64
+    pub fn try_new(duration: u64, profile: &[u64]) -> Result<Problem, Error> {
+        let ground_max = match profile.iter().max() {
+            Some(&max) => max,
+            None => return Err(Error::EmptyProfile),
+        };
+        let size = profile.len() as u64;
+        let fits = duration.checked_mul(size).is_some()
+            && ground_max.checked_mul(size).is_some()
+            && profile
+                .iter()
+                .try_fold(0u64, |a, &g| a.checked_add(g))
+                .is_some();
+        if !fits {
+            return Err(Error::Overflow);
+        }
+        return Ok(Problem::new(duration, profile));
+    }
+
     pub fn new(duration: u64, profile: &[u64]) -> Problem {
         // convert ground and get some properties
         let grounds: Vec<u64> = profile.to_vec();
-        let ground_min = profile.iter().min().unwrap().clone();
-        let ground_max = profile.iter().max().unwrap().clone();
+        let ground_min = *profile.iter().min().unwrap();
+        let ground_max = *profile.iter().max().unwrap();
         let groundsize = grounds.len();
         let ground_vol = profile.iter().sum();
 
@@ -43,9 +153,125 @@ impl Problem {
             saturation_water,
         }
     }
+
+    // number of segments
+    pub fn groundsize(&self) -> usize {
+        self.groundsize
+    }
+
+    // lowest ground
+    pub fn ground_min(&self) -> u64 {
+        self.ground_min
+    }
+
+    // sum of the ground heights, the volume of land above zero
+    pub fn ground_vol(&self) -> u64 {
+        self.ground_vol
+    }
+
+    // amount of water to fill all wells level with the highest peak
+    pub fn saturation_water(&self) -> u64 {
+        self.saturation_water
+    }
+}
+
+// Boundary at both ends of the profile
+// Closed: impermeable walls, all rain stays on the profile
+// Open: water spilling over the outermost peaks runs off and is lost
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Boundary {
+    Closed,
+    Open,
+}
+
+// Solver to use
+// Auto: the trivial cases of select_fn, fill and spill otherwise
+// Raise, Parallel: the trivial cases of select_fn, the divide at watershed
+// algorithm otherwise, on one thread or many, Parallel needs the feature
+// `parallel`
+// Symmetric: the single-pass fill and spill algorithm for all cases
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Solver {
+    Auto,
+    Raise,
+    Parallel,
+    Symmetric,
+}
+
+// Detail of the returned solution
+// Full: levels, water covers and total water
+// Levels: levels and total water, water_covers is left empty
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Detail {
+    Full,
+    Levels,
+}
+
+// Options of solve. tolerance is the relative error in the total amount of
+// water a solution with closed boundaries may have.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub boundary: Boundary,
+    pub tolerance: f64,
+    pub solver: Solver,
+    pub detail: Detail,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            boundary: Boundary::Closed,
+            tolerance: 1e-9,
+            solver: Solver::Auto,
+            detail: Detail::Full,
+        }
+    }
+}
+
+// solve is the one-call entry point: it checks the input, solves the
+// problem of `rain` days on `profile` and checks the result
+pub fn solve(profile: &[u64], rain: u64, options: &Options) -> Result<Solution, Error> {
+    let problem = Problem::try_new(rain, profile)?;
+    let expected = problem.water_tot as f64;
+
+    // the divide at watershed algorithm is made for the general case only
+    let general = solutions::classify(&problem) == solutions::Case::General;
+    let mut solution = match (options.boundary, options.solver) {
+        #[cfg(not(feature = "parallel"))]
+        (Boundary::Closed, Solver::Parallel) => {
+            return Err(Error::Unsupported("built without the parallel feature"))
+        }
+        (Boundary::Closed, Solver::Symmetric) => algorithm::symmetric(problem),
+        (Boundary::Closed, Solver::Raise) if general => algorithm::raise(problem),
+        #[cfg(feature = "parallel")]
+        (Boundary::Closed, Solver::Parallel) if general => algorithm::raise_parallel(problem),
+        (Boundary::Closed, _) => solutions::select_fn(&problem)(problem),
+        (Boundary::Open, _) if problem.ground_max == u64::MAX => return Err(Error::Overflow),
+        (Boundary::Open, Solver::Auto) | (Boundary::Open, Solver::Symmetric) => {
+            let levels = events::levels_open(&problem.grounds, problem.water_0);
+            Solution::new(levels, &problem.grounds)
+        }
+        (Boundary::Open, _) => {
+            return Err(Error::Unsupported(
+                "open boundaries need the symmetric solver",
+            ))
+        }
+    };
+
+    if options.boundary == Boundary::Closed {
+        let received = solution.water_tot;
+        if (received - expected).abs() > options.tolerance * expected.max(1.0) {
+            return Err(Error::Conservation { expected, received });
+        }
+    }
+    if options.detail == Detail::Levels {
+        solution.water_covers = Vec::new();
+    }
+    return Ok(solution);
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow)]
 mod tests {
 
     use super::solutions;
@@ -237,4 +463,119 @@ mod tests {
             assert!(vecf64similar(&received, &expected));
         }
     }
+    #[test]
+    fn solve_options() {
+        let profile = [3, 1, 6, 4, 8, 9];
+        let expected = [4.0, 4.0, 6.0, 6.0, 8.0, 9.0];
+        for solver in [
+            Solver::Auto,
+            Solver::Raise,
+            Solver::Parallel,
+            Solver::Symmetric,
+        ] {
+            let options = Options {
+                solver,
+                ..Options::default()
+            };
+            if solver == Solver::Parallel && !cfg!(feature = "parallel") {
+                assert!(solve(&profile, 1, &options).is_err());
+                continue;
+            }
+            let received = solve(&profile, 1, &options).unwrap();
+            assert!(vecf64similar(&received.levels, &expected));
+            assert_eq!(received.water_covers.len(), 6);
+
+            // flat, dry, saturated and full problems
+            let cases: [(&[u64], u64, f64); 4] = [
+                (&[2, 2, 2], 1, 3.0),
+                (&[1, 5, 1], 10, 37.0 / 3.0),
+                (&[3, 1, 3], 100, 307.0 / 3.0),
+                (&[0, 0], 1, 1.0),
+            ];
+            for &(profile, days, level) in cases.iter() {
+                let received = solve(profile, days, &options).unwrap();
+                assert!(vecf64similar(&received.levels, &vec![level; profile.len()]));
+            }
+        }
+
+        let options = Options {
+            boundary: Boundary::Open,
+            detail: Detail::Levels,
+            ..Options::default()
+        };
+        let received = solve(&[3, 0, 3], 10, &options).unwrap();
+        assert!(vecf64similar(&received.levels, &[3.0, 3.0, 3.0]));
+        assert!(received.water_covers.is_empty());
+        assert!(f64similar(received.water_tot, 3.0));
+
+        let options = Options {
+            boundary: Boundary::Open,
+            solver: Solver::Raise,
+            ..Options::default()
+        };
+        assert!(matches!(
+            solve(&profile, 1, &options),
+            Err(Error::Unsupported(_))
+        ));
+        assert_eq!(
+            solve(&[], 1, &Options::default()).unwrap_err(),
+            Error::EmptyProfile
+        );
+        assert_eq!(
+            solve(&[u64::MAX, 0], 1, &Options::default()).unwrap_err(),
+            Error::Overflow
+        );
+    }
+
+    #[test]
+    fn accessors() {
+        let problem = Problem::new(1, &[3, 1, 6, 4, 8, 9]);
+        assert_eq!(problem.groundsize(), 6);
+        assert_eq!(problem.ground_min(), 1);
+        assert_eq!(problem.ground_vol(), 31);
+        assert_eq!(problem.saturation_water(), 23);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let problem = Problem::new(1, &[3, 1, 6, 4, 8, 9]);
+        let json = serde_json::to_string(&problem).unwrap();
+        let received: Problem = serde_json::from_str(&json).unwrap();
+        assert_eq!(received.grounds, problem.grounds);
+        assert_eq!(received.saturation_water(), 23);
+        assert_eq!(json, r#"{"duration":1,"grounds":[3,1,6,4,8,9]}"#);
+
+        // derived values are computed, not taken from the input
+        let json = r#"{"duration":2,"grounds":[2,0],"water_tot":0,"ground_max":9}"#;
+        let received: Problem = serde_json::from_str(json).unwrap();
+        assert_eq!((received.water_tot, received.ground_max), (4, 2));
+        assert_eq!(received.saturation_water(), 2);
+        assert!(serde_json::from_str::<Problem>(r#"{"duration":1,"grounds":[]}"#).is_err());
+        let overflow = format!(r#"{{"duration":{},"grounds":[1,2]}}"#, u64::MAX);
+        assert!(serde_json::from_str::<Problem>(&overflow).is_err());
+
+        let solution = solutions::select_fn(&received)(received);
+        let json = serde_json::to_string(&solution).unwrap();
+        let received: solutions::Solution = serde_json::from_str(&json).unwrap();
+        assert_eq!(received.levels, solution.levels);
+        assert_eq!(received.water_covers, solution.water_covers);
+    }
+
+    #[test]
+    fn solve_symmetric() {
+        let cases = [
+            provide_cases("simple"),
+            provide_cases("saturation"),
+            provide_cases("all"),
+        ]
+        .concat();
+        for case in cases.iter() {
+            let Case(a, b, expected) = case;
+            let problem = Problem::new(*a, b);
+            let received = algorithm::symmetric(problem).levels;
+
+            assert!(vecf64similar(&received, expected));
+        }
+    }
 }
@@ -1,34 +1,344 @@
+#![allow(clippy::needless_return)]
+
+use std::collections::BTreeMap;
 use std::env;
+use std::fs;
 use std::process;
 
-use rain::input::{Config, Data, Rawinput};
-use rain::solutions;
-use rain::Problem;
+use rain::alerts::{self, Status};
+use rain::damage;
+use rain::events;
+use rain::generate;
+use rain::input::{Command, Config, Data, Rawinput};
+use rain::lakes::{self, Bound};
+use rain::sloped;
+use rain::solutions::Solution;
+use rain::stats::Stats;
+use rain::{Boundary, Problem};
+
+// exit status when water at a location is deeper than its critical depth,
+// errors exit with 1
+const ALERT: i32 = 2;
 
 fn main() {
     // get config from comand line arguments
     let args: Vec<String> = env::args().collect();
-    let config = Config::new(&args, "example.toml");
+    let config = Config::try_new(&args, "example.toml").unwrap_or_else(|err| {
+        eprintln!("Invalid arguments: {}", err);
+        process::exit(1);
+    });
 
+    match config.command {
+        Command::Solve => solve(config),
+        Command::Generate => generate(config),
+        Command::Stats => stats(config),
+        Command::Lakes => lakes(config),
+        Command::Damage => damage(config),
+    }
+}
+
+// read_data reads and parses the input file, exits on failure
+fn read_data(config: Config) -> Data {
     // read raw input
     let rawinput = Rawinput::new(config).unwrap_or_else(|err| {
-        eprintln!("Input file not found: {}", err);
+        eprintln!("Input file cannot be read: {}", err);
         process::exit(1);
     });
 
-    // parse toml to struct Data
-    let data = Data::new(rawinput).unwrap_or_else(|err| {
-        eprintln!("Input .toml cannot be parsed: {}", err);
+    // parse toml, yaml or json to struct Data
+    return Data::new(rawinput).unwrap_or_else(|err| {
+        eprintln!("Input cannot be parsed: {}", err);
         process::exit(1);
     });
+}
+
+// solve reads a profile and prints the resulting levels
+fn solve(config: Config) {
+    let sloped = match config.options.get("terrain").map(|t| t.as_str()) {
+        None | Some("blocks") => false,
+        Some("sloped") => true,
+        Some(other) => {
+            eprintln!("Unknown terrain {}, one of: blocks, sloped", other);
+            process::exit(1);
+        }
+    };
+    let data = read_data(config);
+    if sloped {
+        return solve_sloped(data);
+    }
+
+    // calculate results and print them, one per profile entry
+    let levels = flood(&data, data.duration).unwrap_or_else(|err| {
+        eprintln!("Cannot solve: {}", err);
+        process::exit(1);
+    });
+    println!("Resulting absolute water levels and ground levels:");
+    match data.datum {
+        // transects of elevation grids report elevations
+        Some(datum) => {
+            let elevations: Vec<f64> = levels.iter().map(|&l| datum.elevation(l)).collect();
+            println!("{:?}", elevations);
+        }
+        None => println!("{:?}", levels),
+    }
+    let covers: Vec<f64> = levels
+        .iter()
+        .zip(data.profile.iter())
+        .map(|(&level, &ground)| level - ground as f64)
+        .collect();
+    watch(&data, &covers);
+}
+
+// watch reports on the locations of the input and exits with ALERT if the
+// water at any of them is deeper than its critical depth
+fn watch(data: &Data, depths: &[f64]) {
+    if data.locations.is_empty() {
+        return;
+    }
+    // depths of transects in units of elevation
+    let scale = data.datum.map_or(1.0, |datum| datum.resolution);
+    let depths: Vec<f64> = depths.iter().map(|d| d * scale).collect();
+    let reports = alerts::check(&data.locations, &depths).unwrap_or_else(|err| {
+        eprintln!("Cannot check locations: {}", err);
+        process::exit(1);
+    });
+
+    println!("Locations:");
+    for report in reports.iter() {
+        let status = match (report.status, report.excess) {
+            (Status::Dry, _) => "dry".to_string(),
+            (Status::Wet, None) => format!("wet, {} deep", report.depth),
+            (Status::Wet, Some(excess)) => {
+                format!("wet, {} deep, {} below critical", report.depth, -excess)
+            }
+            (Status::Alert, excess) => format!(
+                "ALERT, {} deep, {} over critical",
+                report.depth,
+                excess.unwrap_or_default()
+            ),
+        };
+        println!("{} ({}): {}", report.name, report.position, status);
+    }
+    if alerts::alert(&reports) {
+        process::exit(ALERT);
+    }
+}
+
+// flood solves the input for a duration and returns the level of every
+// profile entry. Entries are weighted by their widths, without x-coordinates
+// every entry is a unit segment.
+fn flood(data: &Data, duration: u64) -> Result<Vec<f64>, rain::Error> {
+    let widths = match &data.widths {
+        Some(widths) => widths.clone(),
+        None => vec![1.0; data.profile.len()],
+    };
+    return events::levels_widths(&data.profile, &widths, duration, Boundary::Closed);
+}
+
+// unit_segments exits if the profile has x-coordinates, for the commands
+// that take unit segments only
+fn unit_segments(data: &Data, command: &str) {
+    if data.widths.is_some() {
+        eprintln!("{} takes no x-coordinates", command);
+        process::exit(1);
+    }
+}
+
+// solve_sloped takes the profile as vertices of linear ground and prints
+// the levels at the vertices and the shores of the lakes
+fn solve_sloped(data: Data) {
+    unit_segments(&data, "Sloped terrain");
+    let sloped =
+        sloped::solve(&data.profile, data.duration, Boundary::Closed).unwrap_or_else(|err| {
+            eprintln!("Cannot solve: {}", err);
+            process::exit(1);
+        });
+
+    println!("Resulting absolute water levels and ground levels at the vertices:");
+    match data.datum {
+        Some(datum) => {
+            let elevations: Vec<f64> = sloped
+                .solution
+                .levels
+                .iter()
+                .map(|&l| datum.elevation(l))
+                .collect();
+            println!("{:?}", elevations);
+        }
+        None => println!("{:?}", sloped.solution.levels),
+    }
+    println!("Shores of the lakes:");
+    println!("{:?}", sloped.shores);
+    watch(&data, &sloped.solution.water_covers);
+}
 
-    // pre-process data and get struct describing problem
+// stats prints facts about the profile without solving it
+fn stats(config: Config) {
+    let data = read_data(config);
+    unit_segments(&data, "The stats command");
     let problem = Problem::new(data.duration, &data.profile);
+    let stats = Stats::new(&problem);
 
-    // this provides the adequate function to solve a given problem
-    let solver = solutions::select_fn(&problem);
+    println!("length:             {}", stats.length);
+    println!("min:                {}", stats.min);
+    println!("max:                {}", stats.max);
+    println!("ground volume:      {}", stats.ground_volume);
+    println!("saturation water:   {}", stats.saturation_water);
+    println!("saturation days:    {}", stats.saturation_days);
+    println!("peaks:              {}", stats.peaks);
+    println!("wells:              {}", stats.wells);
+    println!("plateaus:           {}", stats.plateaus);
+    println!("largest capacity:   {}", stats.largest_capacity);
+    println!(
+        "case:               {:?} ({} days)",
+        stats.case, data.duration
+    );
+}
 
-    // calculate results and print them
-    println!("Resulting absolute water levels and ground levels:");
-    println!("{:?}", solver(problem).levels);
+// lakes solves a profile and prints one line per lake, separated by tabs
+fn lakes(config: Config) {
+    let data = read_data(config);
+    unit_segments(&data, "The lakes command");
+    let levels = flood(&data, data.duration).unwrap_or_else(|err| {
+        eprintln!("Cannot solve: {}", err);
+        process::exit(1);
+    });
+    let solution = Solution::new(levels, &data.profile);
+    let lakes = lakes::lakes(&data.profile, &solution);
+
+    // transects of elevation grids report the surface as elevation
+    let surface = |level: f64| match &data.datum {
+        Some(datum) => datum.elevation(level),
+        None => level,
+    };
+    let bound = |bound: Bound| match bound {
+        Bound::Wall => "wall".to_string(),
+        Bound::Shore(i) => format!("shore {}", i),
+        Bound::Peak(i) => format!("peak {}", i),
+    };
+    println!("start\tend\twidth\tlevel\tmax depth\tmean depth\tvolume\tleft\tright");
+    for lake in lakes.iter() {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            lake.start,
+            lake.end,
+            lake.width,
+            surface(lake.level),
+            lake.max_depth,
+            lake.mean_depth,
+            lake.volume,
+            bound(lake.left),
+            bound(lake.right)
+        );
+    }
+}
+
+// damage prices the flood of a profile with the damage of its input, per
+// entry and in total. With --durations D,D,.. it prints the total damage
+// for each duration instead.
+fn damage(config: Config) {
+    let durations: Option<Vec<u64>> = config.options.get("durations").map(|list| {
+        list.split(',')
+            .map(|d| {
+                d.trim().parse().unwrap_or_else(|err| {
+                    eprintln!("--durations {}: {}", list, err);
+                    process::exit(1);
+                })
+            })
+            .collect()
+    });
+    let data = read_data(config);
+    let damages = match &data.damage {
+        Some(damages) => damages,
+        None => {
+            eprintln!("Input has no damage");
+            process::exit(1);
+        }
+    };
+    // damages are per unit of width, an entry without width is one segment
+    let widths = match &data.widths {
+        Some(widths) => widths.clone(),
+        None => vec![1.0; data.profile.len()],
+    };
+    let fail = |err: rain::Error| -> ! {
+        eprintln!("Cannot assess damage: {}", err);
+        process::exit(1);
+    };
+
+    if let Some(durations) = durations {
+        let totals =
+            damage::sweep(&data.profile, &widths, damages, &durations).unwrap_or_else(|e| fail(e));
+        println!("days\tdamage");
+        for (duration, total) in durations.iter().zip(totals.iter()) {
+            println!("{}\t{}", duration, total);
+        }
+        return;
+    }
+
+    let assessment =
+        damage::flood(&data.profile, &widths, damages, data.duration).unwrap_or_else(|e| fail(e));
+    println!("Damage per profile entry:");
+    println!("{:?}", assessment.segments);
+    println!("Total damage: {}", assessment.total);
+}
+
+// generate writes a synthetic profile as toml input, to stdout or --output
+// rain generate <kind> [--length N] [--low H] [--high H] [--seed S]
+//     [--period N] [--roughness R] [--duration D] [--output FILE]
+fn generate(config: Config) {
+    let kind = config.positional.first().cloned().unwrap_or_else(|| {
+        eprintln!("Missing generator, one of: {}", generate::KINDS.join(", "));
+        process::exit(1);
+    });
+
+    let data = generate_data(&kind, &config).unwrap_or_else(|err| {
+        eprintln!("Cannot generate profile: {}", err);
+        process::exit(1);
+    });
+    let toml = data.to_toml().unwrap_or_else(|err| {
+        eprintln!("Cannot write .toml: {}", err);
+        process::exit(1);
+    });
+
+    match config.options.get("output") {
+        Some(filename) => fs::write(filename, toml).unwrap_or_else(|err| {
+            eprintln!("Output file cannot be written: {}", err);
+            process::exit(1);
+        }),
+        None => print!("{}", toml),
+    }
+}
+
+fn generate_data(kind: &str, config: &Config) -> Result<Data, Box<dyn std::error::Error>> {
+    let default = generate::Params::default();
+    let params = generate::Params {
+        length: config.option("length")?.unwrap_or(default.length),
+        low: config.option("low")?.unwrap_or(default.low),
+        high: config.option("high")?.unwrap_or(default.high),
+        seed: config.option("seed")?.unwrap_or(default.seed),
+        period: config.option("period")?.unwrap_or(default.period),
+        roughness: config.option("roughness")?.unwrap_or(default.roughness),
+    };
+    if params.length == 0 {
+        return Err("length must be positive".into());
+    }
+    if params.low > params.high {
+        return Err("low must not exceed high".into());
+    }
+    let profile = match generate::profile(kind, &params) {
+        Some(profile) => profile,
+        None => {
+            let kinds = generate::KINDS.join(", ");
+            return Err(format!("unknown generator {}, one of: {}", kind, kinds).into());
+        }
+    };
+    let duration = config.option("duration")?.unwrap_or(1);
+    return Ok(Data {
+        duration,
+        profile,
+        widths: None,
+        datum: None,
+        locations: BTreeMap::new(),
+        damage: None,
+    });
 }
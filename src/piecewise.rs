@@ -0,0 +1,190 @@
+// module piecewise gives the level of every segment as a function of the
+// rain duration. Between two events of the fill-and-spill simulation in
+// module events every lake rises at a constant rate, hence the level of a
+// segment is piecewise linear in the duration of rain. Once the functions
+// are built any duration is evaluated by a binary search over the
+// breakpoints, without solving the problem again.
+//
+// The functions follow the same fill-and-spill model as solutions::select_fn,
+// evaluated at a whole number of days they give its levels.
+use crate::events::{capacities, Engine};
+use crate::tree::MergeTree;
+
+// LevelFunction of a single segment: from breakpoints[k] on, the level
+// starts at levels[k] and rises by slopes[k] per day of rain.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelFunction {
+    pub breakpoints: Vec<f64>,
+    pub levels: Vec<f64>,
+    pub slopes: Vec<f64>,
+}
+
+impl LevelFunction {
+    // level after duration days of rain
+    pub fn eval(&self, duration: f64) -> f64 {
+        let k = self.breakpoints.partition_point(|&b| b <= duration).max(1) - 1;
+        return self.levels[k] + self.slopes[k] * (duration - self.breakpoints[k]);
+    }
+
+    // append a piece, a later piece at the same time replaces the earlier
+    fn push(&mut self, time: f64, level: f64, slope: f64) {
+        if self.breakpoints.last() == Some(&time) {
+            self.breakpoints.pop();
+            self.levels.pop();
+            self.slopes.pop();
+        }
+        if self.slopes.last() == Some(&slope) {
+            return;
+        }
+        self.breakpoints.push(time);
+        self.levels.push(level);
+        self.slopes.push(slope);
+    }
+}
+
+// level_functions returns the level function of each segment of a profile.
+// The number of breakpoints of a segment is bounded by the number of lakes
+// it is part of, which is the depth of the merge tree at this segment.
+pub fn level_functions(grounds: &[u64]) -> Vec<LevelFunction> {
+    let tree = MergeTree::new(grounds);
+    let rain = vec![1.0; grounds.len()];
+    let mut engine = Engine::new(&tree, &rain, capacities(&tree), true);
+    engine.advance(f64::INFINITY);
+    let trace = engine.trace.take().unwrap();
+
+    let mut prefix = vec![0.0; grounds.len() + 1];
+    for (i, &g) in grounds.iter().enumerate() {
+        prefix[i + 1] = prefix[i] + g as f64;
+    }
+
+    // a segment first gets wet in the smallest lake that contains it
+    let mut owner = vec![tree.root; grounds.len()];
+    for (id, node) in tree.nodes.iter().enumerate() {
+        let mut i = node.start;
+        for &child in node.children.iter() {
+            while i < tree.nodes[child].start {
+                owner[i] = id;
+                i += 1;
+            }
+            i = tree.nodes[child].end + 1;
+        }
+        while i <= node.end {
+            owner[i] = id;
+            i += 1;
+        }
+    }
+
+    return grounds
+        .iter()
+        .enumerate()
+        .map(|(i, &g)| {
+            // dry until its lake forms, then it follows the lake and all
+            // lakes this one joins
+            let mut function = LevelFunction {
+                breakpoints: vec![0.0],
+                levels: vec![g as f64],
+                slopes: vec![0.0],
+            };
+            let mut lake = Some(owner[i]);
+            while let Some(id) = lake {
+                let node = &tree.nodes[id];
+                let width = (node.end - node.start + 1) as f64;
+                let land = prefix[node.end + 1] - prefix[node.start];
+                for piece in trace[id].iter() {
+                    if piece.full {
+                        let level = tree.nodes[node.parent.unwrap()].level as f64;
+                        function.push(piece.time, level, 0.0);
+                    } else {
+                        let level = (piece.volume + land) / width;
+                        function.push(piece.time, level, piece.rate / width);
+                    }
+                }
+                lake = node.parent;
+            }
+            function
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solutions::select_fn;
+    use crate::zero::{f64similar, vecf64similar};
+    use crate::Problem;
+
+    fn levels_at(functions: &[LevelFunction], duration: f64) -> Vec<f64> {
+        return functions.iter().map(|f| f.eval(duration)).collect();
+    }
+
+    #[test]
+    fn evaluate_durations() {
+        let functions = level_functions(&[3, 1, 6, 4, 8, 9]);
+        let expected_0 = vec![3.0, 1.0, 6.0, 4.0, 8.0, 9.0];
+        let expected_1 = vec![4.0, 4.0, 6.0, 6.0, 8.0, 9.0];
+        let expected_2 = vec![6.5, 6.5, 6.5, 6.5, 8.0, 9.0];
+        assert!(vecf64similar(&levels_at(&functions, 0.0), &expected_0));
+        assert!(vecf64similar(&levels_at(&functions, 1.0), &expected_1));
+        assert!(vecf64similar(&levels_at(&functions, 2.0), &expected_2));
+
+        let functions = level_functions(&[0, 5, 0, 5, 0, 5, 0]);
+        let expected = vec![3.0, 5.0, 4.0, 5.0, 4.0, 5.0, 3.0];
+        assert!(vecf64similar(&levels_at(&functions, 2.0), &expected));
+    }
+
+    #[test]
+    fn above_saturation() {
+        let functions = level_functions(&[5, 5, 0, 0, 0, 0, 5, 5]);
+        let expected = vec![5.5; 8];
+        assert!(vecf64similar(&levels_at(&functions, 3.0), &expected));
+    }
+
+    #[test]
+    fn breakpoints() {
+        let functions = level_functions(&[0, 3, 0]);
+        // the peak stays dry until both wells reach it after 2 days
+        assert_eq!(functions[1].breakpoints, vec![0.0, 2.0]);
+        assert_eq!(functions[1].slopes, vec![0.0, 1.0]);
+        assert_eq!(functions[0].slopes, vec![1.5, 1.0]);
+        for f in functions.iter() {
+            assert!(f.slopes.iter().all(|&s| s >= 0.0));
+        }
+    }
+
+    #[test]
+    fn water_conservation() {
+        // all profiles of up to five segments of heights 0 to 3
+        for size in 1..=5u32 {
+            for code in 0..4u64.pow(size) {
+                let grounds: Vec<u64> = (0..size).map(|k| code / 4u64.pow(k) % 4).collect();
+                let functions = level_functions(&grounds);
+                for duration in 0..4 {
+                    let levels = levels_at(&functions, duration as f64);
+                    let water: f64 = levels
+                        .iter()
+                        .zip(grounds.iter())
+                        .map(|(l, &g)| l - g as f64)
+                        .sum();
+                    assert!(f64similar(water, (duration * size) as f64));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn agree_with_select_fn() {
+        // all profiles of up to five segments of heights 0 to 3
+        for size in 1..=5u32 {
+            for code in 0..4u64.pow(size) {
+                let grounds: Vec<u64> = (0..size).map(|k| code / 4u64.pow(k) % 4).collect();
+                let functions = level_functions(&grounds);
+                for duration in 0..4 {
+                    let problem = Problem::new(duration, &grounds);
+                    let solved = select_fn(&problem)(problem);
+                    let levels = levels_at(&functions, duration as f64);
+                    assert!(vecf64similar(&levels, &solved.levels));
+                }
+            }
+        }
+    }
+}
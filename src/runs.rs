@@ -0,0 +1,184 @@
+// module runs stores a profile as runs of equal heights and solves it
+// without expanding the runs. A run of width w stands for w adjacent
+// segments of the same height; they lie in the same lake or are dry
+// together, so a run has a single level.
+//
+// The merge tree of the run heights has the shape of the tree of the
+// segments, the fill and spill simulation of events::levels_widths runs on
+// it with rain and capacities weighted by the widths. Time and memory grow
+// with the number of runs, not with the number of segments. The levels are
+// those of algorithm::symmetric on the expanded profile.
+use crate::events::levels_widths;
+use crate::solutions::Solution;
+use crate::{Boundary, Error};
+
+// Runs of (height, width), adjacent runs differ in height
+#[derive(Clone, Debug, PartialEq)]
+pub struct Runs {
+    runs: Vec<(u64, u64)>,
+}
+
+// RunSolution holds one level per run and the total amount of water
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunSolution {
+    pub levels: Vec<f64>,
+    pub water_tot: f64,
+}
+
+impl Runs {
+    // new joins adjacent runs of equal height, widths must be positive
+    pub fn new(runs: &[(u64, u64)]) -> Result<Runs, Error> {
+        if runs.is_empty() {
+            return Err(Error::EmptyProfile);
+        }
+        if runs.iter().any(|&(_, width)| width == 0) {
+            return Err(Error::InvalidInput("runs must have a positive width"));
+        }
+        let mut joined: Vec<(u64, u64)> = Vec::new();
+        for &(height, width) in runs.iter() {
+            match joined.last_mut() {
+                Some(last) if last.0 == height => {
+                    last.1 = last.1.checked_add(width).ok_or(Error::Overflow)?
+                }
+                _ => joined.push((height, width)),
+            }
+        }
+        joined
+            .iter()
+            .try_fold(0u64, |a, &(_, w)| a.checked_add(w))
+            .ok_or(Error::Overflow)?;
+        return Ok(Runs { runs: joined });
+    }
+
+    // compress finds the runs of a profile of unit segments
+    pub fn compress(profile: &[u64]) -> Result<Runs, Error> {
+        let runs: Vec<(u64, u64)> = profile.iter().map(|&h| (h, 1)).collect();
+        return Runs::new(&runs);
+    }
+
+    pub fn runs(&self) -> &[(u64, u64)] {
+        return &self.runs;
+    }
+
+    // number of segments the runs stand for
+    pub fn segments(&self) -> u64 {
+        return self.runs.iter().map(|&(_, w)| w).sum();
+    }
+
+    // expand returns the profile of unit segments
+    pub fn expand(&self) -> Vec<u64> {
+        return self
+            .runs
+            .iter()
+            .flat_map(|&(h, w)| std::iter::repeat_n(h, w as usize))
+            .collect();
+    }
+}
+
+impl RunSolution {
+    // expand returns the solution per segment of the expanded profile
+    pub fn expand(&self, runs: &Runs) -> Solution {
+        let levels = runs
+            .runs
+            .iter()
+            .zip(self.levels.iter())
+            .flat_map(|(&(_, w), &l)| std::iter::repeat_n(l, w as usize))
+            .collect();
+        return Solution::new(levels, &runs.expand());
+    }
+}
+
+// solve floods the runs with `duration` days of rain, each segment of a run
+// receives one unit per day
+pub fn solve(runs: &Runs, duration: u64, boundary: Boundary) -> Result<RunSolution, Error> {
+    let heights: Vec<u64> = runs.runs.iter().map(|&(h, _)| h).collect();
+    let widths: Vec<f64> = runs.runs.iter().map(|&(_, w)| w as f64).collect();
+    let levels = levels_widths(&heights, &widths, duration, boundary)?;
+    let water_tot = levels
+        .iter()
+        .zip(runs.runs.iter())
+        .map(|(&l, &(h, w))| (l - h as f64) * w as f64)
+        .sum();
+    return Ok(RunSolution { levels, water_tot });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm;
+    use crate::generate::Rng;
+    use crate::zero::{f64similar, vecf64similar};
+    use crate::{Options, Problem, Solver};
+
+    #[test]
+    fn runs() {
+        let runs = Runs::new(&[(5, 2), (0, 3), (0, 1), (5, 2)]).unwrap();
+        assert_eq!(runs.runs(), [(5, 2), (0, 4), (5, 2)]);
+        assert_eq!(runs.segments(), 8);
+        assert_eq!(runs.expand(), [5, 5, 0, 0, 0, 0, 5, 5]);
+        assert_eq!(Runs::compress(&runs.expand()).unwrap(), runs);
+        assert_eq!(Runs::new(&[]), Err(Error::EmptyProfile));
+        assert!(Runs::new(&[(1, 0)]).is_err());
+        assert_eq!(Runs::new(&[(1, u64::MAX), (2, 1)]), Err(Error::Overflow));
+
+        let received = solve(&runs, 1, Boundary::Closed).unwrap();
+        assert_eq!(received.levels, [5.0, 2.0, 5.0]);
+        assert_eq!(received.water_tot, 8.0);
+        let solution = received.expand(&runs);
+        assert_eq!(solution.levels, [5.0, 5.0, 2.0, 2.0, 2.0, 2.0, 5.0, 5.0]);
+        assert_eq!(solution.water_tot, 8.0);
+    }
+
+    #[test]
+    fn wide_runs() {
+        // a billion segments, the small lake fills from a wide shelf and
+        // spills into the large one
+        let runs = Runs::new(&[
+            (10, 1),
+            (6, 1000),
+            (0, 100),
+            (4, 10),
+            (0, 1_000_000_000),
+            (10, 1),
+        ])
+        .unwrap();
+        let received = solve(&runs, 1, Boundary::Closed).unwrap();
+        let water = runs.segments() as f64;
+        assert!(f64similar(received.water_tot, water));
+        assert_eq!(received.levels[2], 4.0);
+        assert!(f64similar(
+            received.levels[4],
+            (water - 400.0) / 1_000_000_000.0
+        ));
+    }
+
+    #[test]
+    fn expanded_profiles() {
+        let mut rng = Rng::new(3);
+        for _ in 0..500 {
+            let size = rng.range(1, 10) as usize;
+            let pairs: Vec<(u64, u64)> = (0..size)
+                .map(|_| (rng.range(0, 6), rng.range(1, 4)))
+                .collect();
+            let runs = Runs::new(&pairs).unwrap();
+            let profile = runs.expand();
+            let duration = rng.range(0, 3);
+
+            let closed = solve(&runs, duration, Boundary::Closed).unwrap();
+            let expected = algorithm::symmetric(Problem::new(duration, &profile));
+            assert!(vecf64similar(
+                &closed.expand(&runs).levels,
+                &expected.levels
+            ));
+
+            let open = solve(&runs, duration, Boundary::Open).unwrap();
+            let options = Options {
+                boundary: Boundary::Open,
+                solver: Solver::Symmetric,
+                ..Options::default()
+            };
+            let expected = crate::solve(&profile, duration, &options).unwrap();
+            assert!(vecf64similar(&open.expand(&runs).levels, &expected.levels));
+        }
+    }
+}
@@ -0,0 +1,305 @@
+// module sloped floods terrain that is linear between sample points. The
+// heights are vertices at x = 0, 1, .., N, the ground between two of them
+// is a straight line, so a profile of N+1 heights spans N unit intervals.
+// Every interval receives one unit of rain per day, which runs down its
+// slope to the lower vertex; a level interval shares it evenly.
+//
+// The lakes join in the same merge tree as flat-topped segments, built on
+// the vertices, and fill and spill with events::Engine. Only the volume of
+// a lake differs: it also covers the lower part of the sloped intervals at
+// its sides, hence the volume is piecewise quadratic in the level. Volumes,
+// levels and shorelines are exact up to floating point.
+use crate::events::{Engine, State};
+use crate::solutions::Solution;
+use crate::tree::MergeTree;
+use crate::{Boundary, Error};
+
+// SlopedSolution holds the levels and water covers per vertex, whose
+// water_tot sums the covers like any Solution. volume is the exact amount of
+// water over the sloped intervals, shores are the left and right
+// x-positions of every lake.
+#[derive(Debug)]
+pub struct SlopedSolution {
+    pub solution: Solution,
+    pub volume: f64,
+    pub shores: Vec<(f64, f64)>,
+}
+
+// solve floods sloped terrain with `duration` days of rain. With closed
+// walls a lake may end at x = 0 or x = N.
+pub fn solve(vertices: &[u64], duration: u64, boundary: Boundary) -> Result<SlopedSolution, Error> {
+    let size = vertices.len();
+    if size < 2 {
+        return Err(Error::EmptyProfile);
+    }
+    let max = *vertices.iter().max().unwrap();
+    if boundary == Boundary::Open && max == u64::MAX {
+        return Err(Error::Overflow);
+    }
+
+    let levels = match boundary {
+        Boundary::Closed => levels_at(vertices, &rain(vertices), duration as f64, false),
+        // lifted by one between two sinks, like events::levels_open. The
+        // intervals down to the sinks receive no rain.
+        Boundary::Open => {
+            let mut padded = Vec::with_capacity(size + 2);
+            padded.push(0);
+            padded.extend(vertices.iter().map(|&v| v + 1));
+            padded.push(0);
+            let mut rain = vec![0.0];
+            rain.extend(self::rain(vertices));
+            rain.push(0.0);
+            let levels = levels_at(&padded, &rain, duration as f64, true);
+            levels[1..=size].iter().map(|l| l - 1.0).collect()
+        }
+    };
+
+    let volume = (0..size - 1)
+        .map(|i| match wet_part(vertices, &levels, i) {
+            Some((_, _, surface)) => wet(vertices[i], vertices[i + 1], surface),
+            None => 0.0,
+        })
+        .sum();
+    let shores = shores(vertices, &levels);
+    return Ok(SlopedSolution {
+        solution: Solution::new(levels, vertices),
+        volume,
+        shores,
+    });
+}
+
+// volume of water over one interval between grounds a and b when the
+// surface is at level
+pub fn wet(a: u64, b: u64, level: f64) -> f64 {
+    let (lo, hi) = (a.min(b) as f64, a.max(b) as f64);
+    if level <= lo {
+        return 0.0;
+    }
+    if level >= hi {
+        return level - (lo + hi) / 2.0;
+    }
+    return (level - lo) * (level - lo) / (2.0 * (hi - lo));
+}
+
+// rain per vertex: each interval gives its rain to its lower vertex
+fn rain(vertices: &[u64]) -> Vec<f64> {
+    let mut rain = vec![0.0; vertices.len()];
+    for (i, pair) in vertices.windows(2).enumerate() {
+        match pair[0].cmp(&pair[1]) {
+            std::cmp::Ordering::Less => rain[i] += 1.0,
+            std::cmp::Ordering::Greater => rain[i + 1] += 1.0,
+            std::cmp::Ordering::Equal => {
+                rain[i] += 0.5;
+                rain[i + 1] += 0.5;
+            }
+        }
+    }
+    return rain;
+}
+
+// Lake of a node: the intervals from..to it covers, partially at the sides
+struct Lake<'a> {
+    vertices: &'a [u64],
+    from: usize,
+    to: usize,
+}
+
+impl<'a> Lake<'a> {
+    // the intervals next to the vertices start..=end of a node
+    fn new(vertices: &'a [u64], start: usize, end: usize) -> Lake<'a> {
+        return Lake {
+            vertices,
+            from: start.saturating_sub(1),
+            to: end.min(vertices.len() - 2),
+        };
+    }
+
+    fn volume(&self, level: f64) -> f64 {
+        let v = self.vertices;
+        return (self.from..=self.to)
+            .map(|i| wet(v[i], v[i + 1], level))
+            .sum();
+    }
+
+    // level inverts volume. Between two heights of its vertices the volume
+    // is a quadratic polynomial of the level, the one holding the volume is
+    // found by binary search.
+    fn level(&self, volume: f64) -> f64 {
+        let v = self.vertices;
+        let mut heights: Vec<u64> = v[self.from..=self.to + 1].to_vec();
+        heights.sort_unstable();
+        heights.dedup();
+        let below = heights.partition_point(|&h| self.volume(h as f64) <= volume);
+        let base = heights[below.max(1) - 1] as f64;
+
+        // volume = volume(base) + linear * t + square * t^2, t = level - base
+        let (mut linear, mut square) = (0.0, 0.0);
+        for i in self.from..=self.to {
+            let (lo, hi) = (v[i].min(v[i + 1]) as f64, v[i].max(v[i + 1]) as f64);
+            if hi <= base {
+                linear += 1.0;
+            } else if lo <= base {
+                linear += (base - lo) / (hi - lo);
+                square += 1.0 / (2.0 * (hi - lo));
+            }
+        }
+        let rest = (volume - self.volume(base)).max(0.0);
+        if rest == 0.0 {
+            return base;
+        }
+        let root = (linear * linear + 4.0 * square * rest).sqrt();
+        return base + 2.0 * rest / (linear + root);
+    }
+}
+
+// levels of the vertices after duration days of rain, rain per vertex.
+// With sinks the outermost leaves never fill.
+fn levels_at(vertices: &[u64], rain: &[f64], duration: f64, sinks: bool) -> Vec<f64> {
+    let tree = MergeTree::new(vertices);
+    let lakes: Vec<Lake> = tree
+        .nodes
+        .iter()
+        .map(|node| Lake::new(vertices, node.start, node.end))
+        .collect();
+    let last = vertices.len() - 1;
+    let capacity = tree
+        .nodes
+        .iter()
+        .zip(lakes.iter())
+        .map(|(node, lake)| {
+            let sink = sinks && node.children.is_empty() && (node.start == 0 || node.end == last);
+            match node.parent {
+                Some(parent) if !sink => lake.volume(tree.nodes[parent].level as f64),
+                _ => f64::INFINITY,
+            }
+        })
+        .collect();
+    let mut engine = Engine::new(&tree, rain, capacity, false);
+    engine.advance(duration);
+
+    let mut levels: Vec<f64> = vertices.iter().map(|&v| v as f64).collect();
+    for (id, node) in tree.nodes.iter().enumerate() {
+        let level = match engine.state[id] {
+            State::Filling => lakes[id].level(engine.volume_now(id, duration)),
+            State::Full => tree.nodes[node.parent.unwrap()].level as f64,
+            _ => continue,
+        };
+        for l in levels[node.start..=node.end].iter_mut() {
+            *l = level.max(*l);
+        }
+    }
+    return levels;
+}
+
+// wet_part of interval i: the range of x under water and the surface level
+fn wet_part(vertices: &[u64], levels: &[f64], i: usize) -> Option<(f64, f64, f64)> {
+    let (a, b) = (vertices[i] as f64, vertices[i + 1] as f64);
+    let x = i as f64;
+    return match (levels[i] > a, levels[i + 1] > b) {
+        (true, true) => Some((x, x + 1.0, levels[i].max(levels[i + 1]))),
+        (true, false) => Some((x, x + (levels[i] - a) / (b - a), levels[i])),
+        (false, true) => Some((
+            x + 1.0 - (levels[i + 1] - b) / (a - b),
+            x + 1.0,
+            levels[i + 1],
+        )),
+        (false, false) => None,
+    };
+}
+
+// shores joins the wet parts of the intervals to lakes
+fn shores(vertices: &[u64], levels: &[f64]) -> Vec<(f64, f64)> {
+    let mut shores: Vec<(f64, f64)> = Vec::new();
+    for i in 0..vertices.len() - 1 {
+        if let Some((left, right, _)) = wet_part(vertices, levels, i) {
+            match shores.last_mut() {
+                Some(last) if last.1 == left => last.1 = right,
+                _ => shores.push((left, right)),
+            }
+        }
+    }
+    return shores;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::Rng;
+    use crate::zero::{f64similar, vecf64similar};
+
+    #[test]
+    fn valley() {
+        // a lake at level l in [4, 0, 4] holds l^2 / 4
+        let received = solve(&[4, 0, 4], 1, Boundary::Closed).unwrap();
+        let level = 8f64.sqrt();
+        assert!(f64similar(received.solution.levels[1], level));
+        assert_eq!(received.solution.levels[0], 4.0);
+        assert!(f64similar(received.volume, 2.0));
+        let (left, right) = received.shores[0];
+        assert!(f64similar(left, 1.0 - level / 4.0));
+        assert!(f64similar(right, 1.0 + level / 4.0));
+
+        // open: the outer slopes run off, the valley fills up and spills
+        let received = solve(&[0, 4, 0, 4, 0], 1, Boundary::Open).unwrap();
+        assert!(f64similar(received.solution.levels[2], level));
+        assert!(f64similar(received.volume, 2.0));
+        let received = solve(&[0, 4, 0, 4, 0], 3, Boundary::Open).unwrap();
+        assert!(f64similar(received.solution.levels[2], 4.0));
+        assert!(f64similar(received.volume, 4.0));
+        assert_eq!(received.shores, [(1.0, 3.0)]);
+    }
+
+    #[test]
+    fn lake_at_a_wall() {
+        // [6, 0, 3] holds l^2 / 4 up to 3, then spreads to the right wall
+        let received = solve(&[6, 0, 3], 2, Boundary::Closed).unwrap();
+        let level = 102f64.sqrt() - 6.0;
+        assert!(f64similar(received.solution.levels[1], level));
+        assert!(f64similar(received.solution.levels[2], level));
+        assert!(f64similar(received.shores[0].0, 1.0 - level / 6.0));
+        assert_eq!(received.shores[0].1, 2.0);
+
+        // above the highest vertex the water rises evenly
+        let received = solve(&[6, 0, 3], 10, Boundary::Closed).unwrap();
+        assert!(f64similar(received.solution.levels[0], 12.25));
+        assert_eq!(received.shores, [(0.0, 2.0)]);
+
+        assert_eq!(
+            solve(&[5], 1, Boundary::Closed).unwrap_err(),
+            Error::EmptyProfile
+        );
+        let received = solve(&[2, 2, 2], 1, Boundary::Open).unwrap();
+        assert_eq!(received.solution.levels, [2.0; 3]);
+        assert!(received.shores.is_empty());
+    }
+
+    #[test]
+    fn water_conservation() {
+        let mut rng = Rng::new(5);
+        for _ in 0..500 {
+            let size = rng.range(2, 12) as usize;
+            let vertices: Vec<u64> = (0..size).map(|_| rng.range(0, 8)).collect();
+            let duration = rng.range(0, 4);
+            let intervals = (size - 1) as f64;
+
+            let closed = solve(&vertices, duration, Boundary::Closed).unwrap();
+            assert!(f64similar(closed.volume, duration as f64 * intervals));
+            let mut reversed = vertices.clone();
+            reversed.reverse();
+            let mirrored = solve(&reversed, duration, Boundary::Closed).unwrap();
+            let mut levels = mirrored.solution.levels.clone();
+            levels.reverse();
+            assert!(vecf64similar(&levels, &closed.solution.levels));
+
+            let open = solve(&vertices, duration, Boundary::Open).unwrap();
+            assert!(open.volume <= closed.volume + 1e-9);
+            for sloped in [closed, open].iter() {
+                let solution = &sloped.solution;
+                assert!(solution.water_covers.iter().all(|&w| w >= 0.0));
+                let covers: f64 = solution.water_covers.iter().sum();
+                assert_eq!(solution.water_tot, covers);
+                assert!(sloped.shores.windows(2).all(|s| s[0].1 < s[1].0));
+            }
+        }
+    }
+}
@@ -10,6 +10,7 @@ use crate::zero::f64equal;
 // water covers is only the amount of water upon the the land
 // water_tot is the overal amount of water, for plausibility checks
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Solution {
     pub levels: Vec<f64>,
     pub water_covers: Vec<f64>,
@@ -34,31 +35,52 @@ impl Solution {
     }
 }
 
+// Case names the categories of problems select_fn tells apart
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Case {
+    Dry,        // zero days of rain
+    Flat,       // flat world profile
+    Saturation, // water level equal to highest land
+    Full,       // land is entirely under water
+    General,
+}
+
 // categorise problems to deal with trivial and simple problems
-// returns strings as placeholder. Will return function.
-pub fn select_fn(problem: &Problem) -> Box<dyn Fn(Problem) -> Solution> {
+pub fn classify(problem: &Problem) -> Case {
     // zero days of rain
     if f64equal(problem.water_0, 0.0) {
-        return Box::new(|x| dry(x));
+        return Case::Dry;
     }
 
     // flat world profile
     if problem.ground_max == problem.ground_min {
-        return Box::new(|x| flat(x));
+        return Case::Flat;
     }
 
     // saturation, water level equal to highest land
     if problem.water_tot == problem.saturation_water {
-        return Box::new(|x| saturation(x));
+        return Case::Saturation;
     }
 
     // land is entirely under water
     if problem.water_tot > problem.saturation_water {
-        return Box::new(|x| full(x));
+        return Case::Full;
     }
 
-    // function for general case
-    return Box::new(|x| algorithm::raise(x));
+    return Case::General;
+}
+
+// select_fn returns the adequate function for the case of a problem
+pub fn select_fn(problem: &Problem) -> Box<dyn Fn(Problem) -> Solution> {
+    return match classify(problem) {
+        Case::Dry => Box::new(dry),
+        Case::Flat => Box::new(flat),
+        Case::Saturation => Box::new(saturation),
+        Case::Full => Box::new(full),
+        // function for general case, fill and spill without a preferred
+        // direction
+        Case::General => Box::new(algorithm::symmetric),
+    };
 }
 
 // all solver functions must have the same signature:
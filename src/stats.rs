@@ -0,0 +1,95 @@
+// module stats describes a problem without solving it
+use super::Problem;
+use crate::solutions::{classify, Case};
+use crate::wells;
+
+// Stats collects facts about the terrain of a problem. saturation_days is
+// the rain duration after which all wells are level with the highest peak,
+// case is the category select_fn picks for the duration of the problem.
+#[derive(Debug, PartialEq)]
+pub struct Stats {
+    pub length: usize,
+    pub min: u64,
+    pub max: u64,
+    pub ground_volume: u64,
+    pub saturation_water: u64,
+    pub saturation_days: f64,
+    pub peaks: usize,
+    pub wells: usize,
+    pub plateaus: usize,
+    pub largest_capacity: f64,
+    pub case: Case,
+}
+
+impl Stats {
+    pub fn new(p: &Problem) -> Stats {
+        let wells = wells::wells(&p.grounds);
+        let largest_capacity = wells.iter().map(|w| w.capacity).fold(0.0, f64::max);
+
+        Stats {
+            length: p.groundsize,
+            min: p.ground_min,
+            max: p.ground_max,
+            ground_volume: p.ground_vol,
+            saturation_water: p.saturation_water,
+            saturation_days: p.saturation_water as f64 / p.groundsize as f64,
+            peaks: wells::peaks(&p.grounds).len(),
+            wells: wells.len(),
+            plateaus: plateaus(&p.grounds),
+            largest_capacity,
+            case: classify(p),
+        }
+    }
+}
+
+// plateaus counts runs of two or more adjacent segments of equal height
+fn plateaus(grounds: &[u64]) -> usize {
+    let mut count = 0;
+    let mut run = 1;
+    for pair in grounds.windows(2) {
+        if pair[0] == pair[1] {
+            run += 1;
+            if run == 2 {
+                count += 1;
+            }
+        } else {
+            run = 1;
+        }
+    }
+    return count;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_of_profile() {
+        let received = Stats::new(&Problem::new(1, &[5, 5, 0, 0, 0, 0, 5, 5]));
+        let expected = Stats {
+            length: 8,
+            min: 0,
+            max: 5,
+            ground_volume: 20,
+            saturation_water: 20,
+            saturation_days: 2.5,
+            peaks: 2,
+            wells: 1,
+            plateaus: 3,
+            largest_capacity: 20.0,
+            case: Case::General,
+        };
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn trivial_cases() {
+        assert_eq!(Stats::new(&Problem::new(0, &[3, 1, 6])).case, Case::Dry);
+        assert_eq!(Stats::new(&Problem::new(1, &[2, 2])).case, Case::Flat);
+        assert_eq!(
+            Stats::new(&Problem::new(1, &[2, 0, 0, 2])).case,
+            Case::Saturation
+        );
+        assert_eq!(Stats::new(&Problem::new(3, &[2, 0, 0, 2])).case, Case::Full);
+    }
+}
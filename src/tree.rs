@@ -0,0 +1,200 @@
+// module tree builds the merge tree of a profile: the hierarchy in which
+// wells join to ever larger lakes as the water rises. This is the structure
+// that algorithm::recursor walks implicitly when it divides at the highest
+// peak.
+//
+// Every node is a lake that covers a range of segments once the water
+// reaches the highest ground inside that range. The highest segments of the
+// range separate its child lakes, leaves are flat basin floors. The root
+// covers the whole profile, its volume is the saturation water.
+
+// Node is a lake in the merge tree
+// level: water level at which the lake covers its whole range
+// volume: amount of water the range holds at that level, that is the
+// threshold at which the child lakes have joined
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Node {
+    pub start: usize,
+    pub end: usize,
+    pub level: u64,
+    pub volume: f64,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+// MergeTree stores all nodes in a flat vector, they refer to each other by
+// index. Children are ordered from left to right.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MergeTree {
+    pub nodes: Vec<Node>,
+    pub root: usize,
+}
+
+impl MergeTree {
+    // builds the tree in a single pass with a stack of open nodes of
+    // decreasing level; the profile must not be empty
+    pub fn new(grounds: &[u64]) -> MergeTree {
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut stack: Vec<usize> = Vec::new();
+
+        for (i, &g) in grounds.iter().enumerate() {
+            // close all lower nodes, each becomes a child of the next one
+            let mut last: Option<usize> = None;
+            while let Some(&top) = stack.last() {
+                if nodes[top].level >= g {
+                    break;
+                }
+                stack.pop();
+                nodes[top].end = i - 1;
+                if let Some(child) = last {
+                    adopt(&mut nodes, top, child);
+                }
+                last = Some(top);
+            }
+
+            match stack.last() {
+                // another segment of the same level joins an open node
+                Some(&top) if nodes[top].level == g => {
+                    if let Some(child) = last {
+                        adopt(&mut nodes, top, child);
+                    }
+                }
+                _ => {
+                    let start = last.map_or(i, |c| nodes[c].start);
+                    nodes.push(Node {
+                        start,
+                        end: i,
+                        level: g,
+                        volume: 0.0,
+                        parent: None,
+                        children: vec![],
+                    });
+                    let id = nodes.len() - 1;
+                    if let Some(child) = last {
+                        adopt(&mut nodes, id, child);
+                    }
+                    stack.push(id);
+                }
+            }
+        }
+
+        // whatever remains open reaches the right boundary
+        let mut last: Option<usize> = None;
+        while let Some(top) = stack.pop() {
+            nodes[top].end = grounds.len() - 1;
+            if let Some(child) = last {
+                adopt(&mut nodes, top, child);
+            }
+            last = Some(top);
+        }
+        let root = last.expect("profile must not be empty");
+
+        let mut prefix = vec![0.0; grounds.len() + 1];
+        for (i, &g) in grounds.iter().enumerate() {
+            prefix[i + 1] = prefix[i] + g as f64;
+        }
+        for node in nodes.iter_mut() {
+            let width = (node.end - node.start + 1) as f64;
+            let land = prefix[node.end + 1] - prefix[node.start];
+            node.volume = node.level as f64 * width - land;
+        }
+
+        return MergeTree { nodes, root };
+    }
+
+    // leaves are the lakes that start filling first
+    pub fn leaves(&self) -> Vec<usize> {
+        return (0..self.nodes.len())
+            .filter(|&i| self.nodes[i].children.is_empty())
+            .collect();
+    }
+
+    // export the tree as JSON
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        return serde_json::to_string_pretty(self);
+    }
+
+    // export the tree as a Graphviz DOT digraph
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph merge_tree {\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            dot.push_str(&format!(
+                "    n{} [label=\"{}..{}\\nlevel {}\\nvolume {}\"];\n",
+                i, node.start, node.end, node.level, node.volume
+            ));
+        }
+        for (i, node) in self.nodes.iter().enumerate() {
+            for child in node.children.iter() {
+                dot.push_str(&format!("    n{} -> n{};\n", i, child));
+            }
+        }
+        dot.push_str("}\n");
+        return dot;
+    }
+}
+
+// adopt appends child to the children of parent
+fn adopt(nodes: &mut [Node], parent: usize, child: usize) {
+    nodes[parent].children.push(child);
+    nodes[child].parent = Some(parent);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_tree() {
+        let grounds = vec![3, 1, 6, 4, 8, 9];
+        let tree = MergeTree::new(&grounds);
+        let root = &tree.nodes[tree.root];
+        assert_eq!((root.start, root.end, root.level), (0, 5, 9));
+        assert_eq!(root.volume, 23.0);
+        assert_eq!(root.parent, None);
+
+        // the lake that joins the wells left and right of the 6
+        let split = tree.nodes.iter().find(|n| n.level == 6).unwrap();
+        assert_eq!((split.start, split.end), (0, 3));
+        assert_eq!(split.volume, 10.0);
+        let children: Vec<(usize, usize)> = split
+            .children
+            .iter()
+            .map(|&c| (tree.nodes[c].start, tree.nodes[c].end))
+            .collect();
+        assert_eq!(children, vec![(0, 1), (3, 3)]);
+        assert_eq!(tree.leaves().len(), 2);
+    }
+
+    #[test]
+    fn equal_peaks_share_a_node() {
+        let tree = MergeTree::new(&[5, 0, 5, 0, 5]);
+        let root = &tree.nodes[tree.root];
+        assert_eq!(root.level, 5);
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(tree.nodes.len(), 3);
+
+        let flat = MergeTree::new(&[2, 2, 2]);
+        assert_eq!(flat.nodes.len(), 1);
+        assert_eq!(flat.nodes[flat.root].volume, 0.0);
+    }
+
+    #[test]
+    fn export_tree() {
+        let tree = MergeTree::new(&[0, 3, 0]);
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph merge_tree {"));
+        assert_eq!(dot.matches("->").count(), 2);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn export_json() {
+        let tree = MergeTree::new(&[0, 3, 0]);
+        let json: serde_json::Value = serde_json::from_str(&tree.to_json().unwrap()).unwrap();
+        assert_eq!(json["nodes"].as_array().unwrap().len(), 3);
+        assert_eq!(json["root"], tree.root);
+    }
+}
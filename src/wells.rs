@@ -0,0 +1,200 @@
+// module wells decomposes a profile into its peaks and the wells between
+// them, following the definitions in README.md. It does not simulate rain,
+// it only describes the terrain.
+use crate::algorithm::well_volume;
+
+// Side names the direction into which a well spills once it is full
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+    Both,
+}
+
+// Peak is a run of adjacent segments of equal height whose neighbours are
+// all lower. The impermeable boundaries are not neighbours, a run touching
+// a boundary is a peak if its other neighbour is lower.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Peak {
+    pub start: usize,
+    pub end: usize,
+    pub height: u64,
+}
+
+// Well is the range of segments between two peaks, or between a peak and a
+// boundary. Bounding peaks are None where the well touches a boundary.
+// spill_height is the level at which the well overflows to spill_side,
+// capacity is the water it holds up to that level and catchment is the
+// width of land whose rain runs into it: the well itself, half of each
+// bounding peak, or all of a peak that leans against a boundary.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Well {
+    pub start: usize,
+    pub end: usize,
+    pub left_peak: Option<u64>,
+    pub right_peak: Option<u64>,
+    pub spill_height: u64,
+    pub spill_side: Side,
+    pub capacity: f64,
+    pub catchment: f64,
+}
+
+// peaks returns all peaks of a profile from left to right
+pub fn peaks(grounds: &[u64]) -> Vec<Peak> {
+    let mut peaks = Vec::new();
+    let mut start = 0;
+    while start < grounds.len() {
+        let height = grounds[start];
+        let width = grounds[start..]
+            .iter()
+            .take_while(|&&g| g == height)
+            .count();
+        let end = start + width - 1;
+
+        let left_lower = start == 0 || grounds[start - 1] < height;
+        let right_lower = end == grounds.len() - 1 || grounds[end + 1] < height;
+        if left_lower && right_lower {
+            peaks.push(Peak { start, end, height });
+        }
+        start = end + 1;
+    }
+    return peaks;
+}
+
+// wells returns all wells of a profile from left to right, an empty
+// profile has none
+pub fn wells(grounds: &[u64]) -> Vec<Well> {
+    if grounds.is_empty() {
+        return Vec::new();
+    }
+    let peaks = peaks(grounds);
+    let last = grounds.len() - 1;
+
+    // a peak leaning against a boundary drains entirely into its only well
+    let share = |p: &Peak| -> f64 {
+        let width = (p.end - p.start + 1) as f64;
+        if p.start == 0 || p.end == last {
+            width
+        } else {
+            width / 2.0
+        }
+    };
+
+    // pairs of neighbouring peaks, with the boundaries as outermost bounds
+    let mut bounds: Vec<(Option<&Peak>, Option<&Peak>)> = Vec::new();
+    if let Some(first) = peaks.first() {
+        bounds.push((None, Some(first)));
+    }
+    for pair in peaks.windows(2) {
+        bounds.push((Some(&pair[0]), Some(&pair[1])));
+    }
+    if let Some(last) = peaks.last() {
+        bounds.push((Some(last), None));
+    }
+
+    let mut wells = Vec::new();
+    for (left, right) in bounds {
+        if right.is_some_and(|p| p.start == 0) || left.is_some_and(|p| p.end == last) {
+            // the peak itself touches the boundary, there is no well
+            continue;
+        }
+        let start = left.map_or(0, |p| p.end + 1);
+        let end = right.map_or(last + 1, |p| p.start) - 1;
+
+        let left_peak = left.map(|p| p.height);
+        let right_peak = right.map(|p| p.height);
+        let (spill_height, spill_side) = match (left_peak, right_peak) {
+            (Some(l), Some(r)) if l == r => (l, Side::Both),
+            (Some(l), Some(r)) if l < r => (l, Side::Left),
+            (Some(l), None) => (l, Side::Left),
+            (_, Some(r)) => (r, Side::Right),
+            (None, None) => unreachable!("every profile has a peak"),
+        };
+
+        // only segments below the spill height hold water
+        let below: Vec<u64> = grounds[start..=end]
+            .iter()
+            .copied()
+            .filter(|&g| g < spill_height)
+            .collect();
+        let capacity = well_volume(&below, spill_height);
+
+        let catchment =
+            (end - start + 1) as f64 + left.map_or(0.0, share) + right.map_or(0.0, share);
+
+        wells.push(Well {
+            start,
+            end,
+            left_peak,
+            right_peak,
+            spill_height,
+            spill_side,
+            capacity,
+            catchment,
+        });
+    }
+    return wells;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_peaks() {
+        let grounds = vec![3, 1, 6, 4, 8, 9];
+        let expected = vec![
+            Peak {
+                start: 0,
+                end: 0,
+                height: 3,
+            },
+            Peak {
+                start: 2,
+                end: 2,
+                height: 6,
+            },
+            Peak {
+                start: 5,
+                end: 5,
+                height: 9,
+            },
+        ];
+        assert_eq!(peaks(&grounds), expected);
+        assert_eq!(peaks(&[2, 2, 2]).len(), 1);
+        assert_eq!(peaks(&[1, 8, 8, 8, 1])[0].end, 3);
+    }
+
+    #[test]
+    fn find_wells() {
+        let received = wells(&[3, 1, 6, 4, 8, 9]);
+        assert_eq!(received.len(), 2);
+        assert_eq!((received[0].start, received[0].end), (1, 1));
+        assert_eq!(received[0].spill_height, 3);
+        assert_eq!(received[0].spill_side, Side::Left);
+        assert_eq!(received[0].capacity, 2.0);
+        assert_eq!(received[0].catchment, 2.5);
+        assert_eq!((received[1].start, received[1].end), (3, 4));
+        assert_eq!(received[1].capacity, 2.0);
+        assert_eq!(received[1].catchment, 3.5);
+    }
+
+    #[test]
+    fn wells_at_boundaries() {
+        let received = wells(&[0, 3, 3, 0]);
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].left_peak, None);
+        assert_eq!(received[0].spill_side, Side::Right);
+        assert_eq!(received[1].right_peak, None);
+        assert_eq!(received[1].spill_side, Side::Left);
+        assert_eq!(received[1].catchment, 2.0);
+
+        let received = wells(&[5, 0, 5, 0, 5]);
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].spill_side, Side::Both);
+        assert_eq!(received[0].catchment, 2.5);
+        assert!(wells(&[4, 4, 4]).is_empty());
+        assert!(wells(&[]).is_empty());
+        assert!(peaks(&[]).is_empty());
+    }
+}
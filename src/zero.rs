@@ -33,7 +33,7 @@ pub fn f64similar(a: f64, b: f64) -> bool {
 }
 
 // compare if two vectors Vec<f64> are equal
-fn vectors(av: &Vec<f64>, bv: &Vec<f64>, epsilon: f64, relth: f64) -> bool {
+fn vectors(av: &[f64], bv: &[f64], epsilon: f64, relth: f64) -> bool {
     // both are empty, as a definition: same
     if av.is_empty() && bv.is_empty() {
         return true;
@@ -48,19 +48,19 @@ fn vectors(av: &Vec<f64>, bv: &Vec<f64>, epsilon: f64, relth: f64) -> bool {
     return av
         .iter()
         .zip(bv.iter())
-        .map(|(&a, &b)| equal(a, b, epsilon, relth))
-        .fold(true, |acc, x| acc && x);
+        .all(|(&a, &b)| equal(a, b, epsilon, relth));
 }
 
-pub fn vecf64equal(av: &Vec<f64>, bv: &Vec<f64>) -> bool {
+pub fn vecf64equal(av: &[f64], bv: &[f64]) -> bool {
     return vectors(av, bv, EPSILON, RELTH);
 }
 
-pub fn vecf64similar(av: &Vec<f64>, bv: &Vec<f64>) -> bool {
+pub fn vecf64similar(av: &[f64], bv: &[f64]) -> bool {
     return vectors(av, bv, TOL * EPSILON, TOL * RELTH);
 }
 
 #[cfg(test)]
+#[allow(clippy::unnecessary_cast)]
 mod tests {
     use super::*;
 